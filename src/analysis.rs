@@ -0,0 +1,219 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use compiler;
+use {Cell, Runtime, RuntimeError, TapeMode};
+
+// something legal but almost certainly unintended, found without running the program
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum Warning {
+	EmptyLoop(usize) // a [] at this offset, which never ends once entered on a nonzero cell
+}
+
+impl Warning {
+
+	// source offset of the first byte the warning is about
+	pub fn offset(&self) -> usize {
+		match *self {
+			Warning::EmptyLoop(offset) => offset
+		}
+	}
+
+	pub fn message(&self) -> &'static str {
+		self.error().message()
+	}
+
+	// what the warning becomes when warnings are treated as errors
+	pub(crate) fn error(&self) -> RuntimeError {
+		match *self {
+			Warning::EmptyLoop(_) => RuntimeError::EmptyLoop
+		}
+	}
+
+}
+
+// what a lint is about; none of these stop a program from running
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum LintCategory {
+	DeadArithmetic, // a run of '+' and '-' that cancels out, or that a ',' overwrites straight after
+	LeadingUnderflow, // a '<' before the pointer has moved right, which always fails on a growable tape
+	UnusedMove // a run of '<' and '>' that cancels out, or that ends the program with no cell left to use
+}
+
+impl LintCategory {
+
+	pub fn message(&self) -> &'static str {
+		match *self {
+			LintCategory::DeadArithmetic => "arithmetic with no effect",
+			LintCategory::LeadingUnderflow => "pointer moved below the first cell",
+			LintCategory::UnusedMove => "pointer move that is never used"
+		}
+	}
+
+}
+
+// a suspect construct found by Runtime::lint(), with the source offset of its first instruction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Lint {
+	pub offset: usize,
+	pub category: LintCategory
+}
+
+// static inspection of the program, none of which executes anything
+impl<C: Cell> Runtime<C> {
+
+	// the real instructions in order, with their byte offsets into the source, comments stripped;
+	// a dialect's tokens are reported as the standard instruction they stand for, and enabled extensions count
+	pub fn disassemble(&self) -> Vec<(usize, char)> {
+		compiler::tokenize(&self.instructions, &self.instruction_set, &self.extensions())
+			.into_iter()
+			.map(|token| (token.offset, token.symbol as char))
+			.collect()
+	}
+
+	// each run of comment text with the offset it starts at; together with disassemble() this covers every source byte
+	pub fn comments(&self) -> Vec<(usize, String)> {
+		let mut comments = Vec::new();
+		let mut start = 0; // end of the previous token
+
+		for token in compiler::tokenize(&self.instructions, &self.instruction_set, &self.extensions()) {
+			if token.offset > start {
+				comments.push((start, String::from_utf8_lossy(&self.instructions[start..token.offset]).into_owned()));
+			}
+			start = token.offset + token.length;
+		}
+
+		if start < self.instructions.len() {
+			comments.push((start, String::from_utf8_lossy(&self.instructions[start..]).into_owned()));
+		}
+
+		comments
+	}
+
+	// the 1-based (line, column) of a source offset such as a snapshot's instruction_pointer, for highlighting it in a
+	// multi-line view; columns count chars rather than bytes, and an offset past the end is the position just after it
+	pub fn source_position(&self, offset: usize) -> (usize, usize) {
+		let before = &self.instructions[..offset.min(self.instructions.len())];
+		let line_start = before.iter().rposition(|&byte| byte == b'\n').map_or(0, |newline| newline + 1);
+		let line = before.iter().filter(|&&byte| byte == b'\n').count() + 1;
+		let column = before[line_start..].iter().filter(|&&byte| (byte & 0xC0) != 0x80).count() + 1; // skipping UTF-8 continuation bytes
+		(line, column)
+	}
+
+	// how many real instructions the source holds, comments excluded, for progress bars and the like; enabled extensions
+	// count, as they do for the maximum instruction count. Static, unlike a product's executions, which counts loop iterations
+	pub fn instruction_count(&self) -> usize {
+		compiler::tokenize(&self.instructions, &self.instruction_set, &self.extensions()).len()
+	}
+
+	// whether the program has any ',' and any '.' at all, e.g. to skip wiring up a stream it never touches;
+	// only says the instruction is present, not that it is ever reached
+	pub fn contains_io(&self) -> (bool, bool) {
+		let tokens = compiler::tokenize(&self.instructions, &self.instruction_set, &[]);
+		(tokens.iter().any(|token| token.symbol == b','), tokens.iter().any(|token| token.symbol == b'.'))
+	}
+
+	// the deepest the brackets nest, 0 for a program without loops; an unbalanced program is an UnmatchedBracket error
+	pub fn max_nesting_depth(&self) -> Result<usize, RuntimeError> {
+		let mut depth: usize = 0;
+		let mut deepest = 0;
+
+		for token in compiler::tokenize(&self.instructions, &self.instruction_set, &[]) {
+			match token.symbol {
+				b'[' => {
+					depth += 1;
+					deepest = deepest.max(depth);
+				},
+				b']' => depth = depth.checked_sub(1).ok_or(RuntimeError::UnmatchedBracket)?,
+				_ => ()
+			}
+		}
+
+		if depth == 0 { Ok(deepest) } else { Err(RuntimeError::UnmatchedBracket) }
+	}
+
+	// likely bugs in the program, in source order; comments between instructions are ignored
+	pub fn analyze(&self) -> Vec<Warning> {
+		self.disassemble()
+			.windows(2)
+			.filter(|pair| (pair[0].1 == '[') && (pair[1].1 == ']'))
+			.map(|pair| Warning::EmptyLoop(pair[0].0))
+			.collect()
+	}
+
+	// constructs that look like mistakes, such as generated code often leaves behind, in source order; purely
+	// diagnostic, so unlike analyze() they never become errors. Comments between instructions are ignored
+	pub fn lint(&self) -> Vec<Lint> {
+		let tokens = self.disassemble();
+		let mut lints = Vec::new();
+
+		if (self.tape_mode == TapeMode::Growable) && !self.grow_left {
+			if let Some(&(offset, '<')) = tokens.iter().find(|&&(_, symbol)| (symbol == '<') || (symbol == '>') || (symbol == '[')) {
+				lints.push(Lint { offset, category: LintCategory::LeadingUnderflow });
+			}
+		}
+
+		let mut start = 0;
+		while start < tokens.len() {
+			let run = |symbols: &[char]| tokens[start..].iter().take_while(|&&(_, symbol)| symbols.contains(&symbol)).count();
+			let net = |up: char, length: usize| tokens[start..(start + length)].iter().fold(0isize, |net, &(_, symbol)| if symbol == up { net + 1 } else { net - 1 });
+
+			let arithmetic = run(&['+', '-']);
+			let moves = run(&['<', '>']);
+			let (length, dead) = if arithmetic > 0 {
+				let overwritten = tokens.get(start + arithmetic).is_some_and(|&(_, symbol)| symbol == ',');
+				(arithmetic, ((net('+', arithmetic) == 0) || overwritten).then_some(LintCategory::DeadArithmetic))
+			} else if moves > 0 {
+				let trailing = tokens[(start + moves)..].is_empty();
+				(moves, ((net('>', moves) == 0) || trailing).then_some(LintCategory::UnusedMove))
+			} else {
+				(1, None)
+			};
+
+			if let Some(category) = dead {
+				lints.push(Lint { offset: tokens[start].0, category });
+			}
+			start += length;
+		}
+
+		lints.sort_by_key(|lint| lint.offset);
+		lints
+	}
+
+	// the first byte that is neither part of an instruction nor whitespace, when in strict mode
+	pub(crate) fn check_strict(&self) -> Result<(), (usize, RuntimeError)> {
+		if !self.strict_mode {
+			return Ok(());
+		}
+
+		let mut in_token = vec![false; self.instructions.len()];
+		for token in compiler::tokenize(&self.instructions, &self.instruction_set, &self.extensions()) {
+			for byte in &mut in_token[token.offset..(token.offset + token.length)] {
+				*byte = true;
+			}
+		}
+
+		match self.instructions.iter().zip(&in_token).position(|(&byte, &in_token)| !in_token && !byte.is_ascii_whitespace()) {
+			Some(offset) => Err((offset, RuntimeError::UnknownInstruction)),
+			None => Ok(())
+		}
+	}
+
+	// the first warning as an error, when warnings are treated as errors
+	pub(crate) fn check_warnings(&self) -> Result<(), (usize, RuntimeError)> {
+		if self.warnings_as_errors {
+			if let Some(warning) = self.analyze().first() {
+				return Err((warning.offset(), warning.error()));
+			}
+		}
+
+		Ok(())
+	}
+
+}
@@ -0,0 +1,125 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::sync::Mutex;
+#[cfg(feature = "std")]
+use std::thread;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use {Cell, RuntimeBuilder, RuntimeProduct, Termination};
+
+// the limits every program of a batch runs under, each 0 meaning unlimited as for a single runtime;
+// set at least an execution or time limit, since candidates from a search often never halt
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BatchLimits {
+	pub execution_limit: usize,
+	pub memory_limit: usize,
+	pub output_limit: usize,
+	pub time_limit_ns: u64,
+	pub threads: usize // programs run at once, 0 for as many as the machine has cores; always 1 without std
+}
+
+// how score_output() compares a product's output with the target
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum OutputMetric {
+	Hamming, // bytes that match position by position, every byte past the shorter of the two counting as wrong
+	Levenshtein // insertions, deletions and substitutions needed to turn one into the other
+}
+
+// fitness of a run for a program search, from 0.0 for nothing right to 1.0 for exactly expected from a run that
+// completed. A run that ended any other way, by an error, a limit or being stopped, is scored as if it owed one
+// more byte, so its output is credited for what is right but it never scores 1.0. Only the buffered output is
+// seen, which is just the tail when output is a ring, and none at all when streamed
+pub fn score_output<C: Cell>(product: &RuntimeProduct<C>, expected: &[u8], metric: OutputMetric) -> f64 {
+	let output = &product.output;
+	let unfinished = if product.termination == Termination::Completed { 0 } else { 1 };
+	let length = output.len().max(expected.len()) + unfinished;
+	if length == 0 {
+		return 1.0; // nothing expected, and nothing output by a complete run
+	}
+
+	let wrong = unfinished + match metric {
+		OutputMetric::Hamming => length - unfinished - output.iter().zip(expected).filter(|&(a, b)| a == b).count(),
+		OutputMetric::Levenshtein => edit_distance(output, expected)
+	};
+	(length - wrong) as f64 / length as f64
+}
+
+// the classic dynamic program, keeping only the previous row
+fn edit_distance(a: &[u8], b: &[u8]) -> usize {
+	let mut previous: Vec<usize> = (0..=b.len()).collect();
+	for (i, &byte) in a.iter().enumerate() {
+		let mut row = Vec::with_capacity(b.len() + 1);
+		row.push(i + 1);
+		for (j, &other) in b.iter().enumerate() {
+			let substitution = previous[j] + if byte == other { 0 } else { 1 };
+			row.push(substitution.min(previous[j + 1] + 1).min(row[j] + 1));
+		}
+		previous = row;
+	}
+	previous[b.len()]
+}
+
+// run each (program, input) pair on its own fresh runtime, returning the products in the same order; nothing is
+// snapshotted, so a product holds only the output and the run's statistics
+pub fn run_batch(programs: Vec<(String, Vec<u8>)>, limits: BatchLimits) -> Vec<RuntimeProduct> {
+	#[cfg(feature = "std")]
+	{
+		let threads = match limits.threads {
+			0 => thread::available_parallelism().map_or(1, |threads| threads.get()),
+			threads => threads
+		}.min(programs.len());
+
+		if threads > 1 {
+			return run_parallel(programs, limits, threads);
+		}
+	}
+
+	programs.into_iter().map(|(instructions, input)| run_one(instructions, input, limits)).collect()
+}
+
+// each worker takes the next program not yet claimed, so one slow program doesn't hold up a whole share of the batch
+#[cfg(feature = "std")]
+fn run_parallel(programs: Vec<(String, Vec<u8>)>, limits: BatchLimits, threads: usize) -> Vec<RuntimeProduct> {
+	let count = programs.len();
+	let queue = Mutex::new(programs.into_iter().enumerate());
+
+	let mut products: Vec<Option<RuntimeProduct>> = (0..count).map(|_| None).collect();
+	thread::scope(|scope| {
+		let workers: Vec<_> = (0..threads).map(|_| scope.spawn(|| {
+			let mut done = Vec::new();
+			loop {
+				let next = queue.lock().unwrap().next(); // unlocked again before the program runs
+				match next {
+					Some((index, (instructions, input))) => done.push((index, run_one(instructions, input, limits))),
+					None => return done
+				}
+			}
+		})).collect();
+
+		for worker in workers {
+			for (index, product) in worker.join().unwrap() {
+				products[index] = Some(product);
+			}
+		}
+	});
+
+	products.into_iter().map(|product| product.unwrap()).collect() // every index was claimed by some worker
+}
+
+fn run_one(instructions: String, input: Vec<u8>, limits: BatchLimits) -> RuntimeProduct {
+	let mut runtime = RuntimeBuilder::new()
+		.instructions(instructions)
+		.input(input)
+		.execution_limit(limits.execution_limit)
+		.memory_limit(limits.memory_limit)
+		.output_limit(limits.output_limit)
+		.time_limit_ns(limits.time_limit_ns)
+		.record_snapshots(false)
+		.build();
+
+	runtime.run()
+}
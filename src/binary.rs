@@ -0,0 +1,457 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::mem;
+#[cfg(feature = "std")]
+use std::io;
+
+use error::FIXED_ERRORS;
+use {Cell, InstructionProfile, RuntimeError, RuntimeProduct, RuntimeSnapshot, Termination};
+
+// a compact alternative to serde for storing and shipping traces: a header, then every field in declaration
+// order, with integers as LEB128 varints and each distinct snapshot message written out only once
+const MAGIC: &[u8] = b"FKEP";
+const VERSION: u8 = 1;
+
+// io::ErrorKind has no stable numbering, so I/O errors are encoded by position here; any other kind comes back as Other
+#[cfg(feature = "std")]
+const IO_KINDS: [io::ErrorKind; 20] = [
+	io::ErrorKind::NotFound,
+	io::ErrorKind::PermissionDenied,
+	io::ErrorKind::ConnectionRefused,
+	io::ErrorKind::ConnectionReset,
+	io::ErrorKind::ConnectionAborted,
+	io::ErrorKind::NotConnected,
+	io::ErrorKind::AddrInUse,
+	io::ErrorKind::AddrNotAvailable,
+	io::ErrorKind::BrokenPipe,
+	io::ErrorKind::AlreadyExists,
+	io::ErrorKind::WouldBlock,
+	io::ErrorKind::InvalidInput,
+	io::ErrorKind::InvalidData,
+	io::ErrorKind::TimedOut,
+	io::ErrorKind::WriteZero,
+	io::ErrorKind::Interrupted,
+	io::ErrorKind::Other,
+	io::ErrorKind::UnexpectedEof,
+	io::ErrorKind::Unsupported,
+	io::ErrorKind::OutOfMemory
+];
+
+// every message a snapshot can carry, so decoding hands back the engine's own strings; keep in step with the instruction handlers
+fn known_messages<C: Cell>() -> Vec<&'static str> {
+	let mut messages = vec![
+		"incremented pointer by 1",
+		"incremented pointer by folded run",
+		"wrapped pointer around to start of tape",
+		"clamped pointer at end of tape",
+		"decremented pointer by 1",
+		"decremented pointer by folded run",
+		"wrapped pointer around to end of tape",
+		"clamped pointer at start of tape",
+		"incremented byte by 1",
+		"incremented byte by folded run",
+		"incremented byte by folded run, wrapping overflow",
+		"decremented byte by 1",
+		"decremented byte by folded run",
+		"decremented byte by folded run, wrapping underflow",
+		"cleared byte to zero",
+		"applied fused multiply loop",
+		"byte is zero, no multiply necessary",
+		"fused multiply loop runs off the tape, running it unfused",
+		"skipped multiply target",
+		"copied byte from memory to output",
+		"copied byte from memory to output, but not to tee",
+		"wrote byte from memory to output sink",
+		"wrote byte from memory to output sink, but not to tee",
+		"flushed output sink",
+		"copied byte from input to memory",
+		"input exhausted, stored zero",
+		"input exhausted, stored maximum cell value",
+		"input exhausted, left byte unchanged",
+		"input exhausted, stored end-of-input value",
+		"found matching close bracket",
+		"byte is non-zero, no bracket seek necessary",
+		"found matching open bracket",
+		"byte is zero, no bracket seek necessary",
+		"switched to next input stream",
+		"debug dump requested",
+		"set byte to zero by '@'",
+		"paused at breakpoint",
+		C::OVERFLOW_MESSAGE,
+		C::UNDERFLOW_MESSAGE,
+		C::SIGNED_OVERFLOW_MESSAGE,
+		C::SIGNED_UNDERFLOW_MESSAGE,
+		RuntimeError::NonAsciiInput(0, 0).message(),
+		RuntimeError::LoopLimitExceeded(0).message()
+	];
+
+	#[cfg(feature = "std")]
+	messages.push(RuntimeError::Io(io::ErrorKind::Other).message());
+
+	messages.extend(FIXED_ERRORS.iter().map(|error| error.message()));
+	messages
+}
+
+pub(crate) struct Encoder {
+	pub(crate) bytes: Vec<u8>
+}
+
+impl Encoder {
+
+	fn number(&mut self, mut value: u64) {
+		while value >= 0x80 {
+			self.bytes.push((value as u8) | 0x80);
+			value >>= 7;
+		}
+		self.bytes.push(value as u8);
+	}
+
+	pub(crate) fn size(&mut self, value: usize) {
+		self.number(value as u64);
+	}
+
+	fn flag(&mut self, value: bool) {
+		self.bytes.push(value as u8);
+	}
+
+	fn blob(&mut self, bytes: &[u8]) {
+		self.size(bytes.len());
+		self.bytes.extend_from_slice(bytes);
+	}
+
+	fn numbers(&mut self, numbers: &[u64]) {
+		self.size(numbers.len());
+		for &number in numbers {
+			self.number(number);
+		}
+	}
+
+	fn error(&mut self, error: RuntimeError) {
+		match error {
+			#[cfg(feature = "std")]
+			RuntimeError::Io(kind) => {
+				self.size(FIXED_ERRORS.len());
+				self.size(IO_KINDS.iter().position(|&known| known == kind).unwrap_or_else(|| IO_KINDS.iter().position(|&known| known == io::ErrorKind::Other).unwrap_or(0)));
+			},
+			RuntimeError::NonAsciiInput(byte, position) => {
+				self.size(FIXED_ERRORS.len() + 1);
+				self.bytes.push(byte);
+				self.size(position);
+			},
+			RuntimeError::LoopLimitExceeded(offset) => {
+				self.size(FIXED_ERRORS.len() + 2);
+				self.size(offset);
+			},
+			_ => self.size(FIXED_ERRORS.iter().position(|&fixed| fixed == error).unwrap_or(0))
+		}
+	}
+
+}
+
+pub(crate) struct Decoder<'a> {
+	pub(crate) bytes: &'a [u8],
+	pub(crate) position: usize
+}
+
+impl<'a> Decoder<'a> {
+
+	pub(crate) fn byte(&mut self) -> Result<u8, RuntimeError> {
+		let byte = *self.bytes.get(self.position).ok_or(RuntimeError::InvalidEncoding)?;
+		self.position += 1;
+		Ok(byte)
+	}
+
+	fn number(&mut self) -> Result<u64, RuntimeError> {
+		let mut value: u64 = 0;
+		for shift in (0..64).step_by(7) {
+			let byte = self.byte()?;
+			if (shift == 63) && (byte > 1) {
+				return Err(RuntimeError::InvalidEncoding); // more than 64 bits
+			}
+
+			value |= u64::from(byte & 0x7F) << shift;
+			if byte & 0x80 == 0 {
+				return Ok(value);
+			}
+		}
+
+		Err(RuntimeError::InvalidEncoding)
+	}
+
+	pub(crate) fn size(&mut self) -> Result<usize, RuntimeError> {
+		let value = self.number()?;
+		if value > usize::MAX as u64 {
+			return Err(RuntimeError::InvalidEncoding);
+		}
+		Ok(value as usize)
+	}
+
+	// a length that the remaining input could possibly hold, so a corrupt one can't make decoding allocate without bound
+	fn length(&mut self) -> Result<usize, RuntimeError> {
+		let length = self.size()?;
+		if length > (self.bytes.len() - self.position) {
+			return Err(RuntimeError::InvalidEncoding);
+		}
+		Ok(length)
+	}
+
+	fn flag(&mut self) -> Result<bool, RuntimeError> {
+		match self.byte()? {
+			0 => Ok(false),
+			1 => Ok(true),
+			_ => Err(RuntimeError::InvalidEncoding)
+		}
+	}
+
+	fn blob(&mut self) -> Result<&'a [u8], RuntimeError> {
+		let length = self.length()?;
+		let blob = &self.bytes[self.position..(self.position + length)];
+		self.position += length;
+		Ok(blob)
+	}
+
+	fn numbers(&mut self) -> Result<Vec<u64>, RuntimeError> {
+		let mut numbers = Vec::new();
+		for _ in 0..self.length()? {
+			numbers.push(self.number()?);
+		}
+		Ok(numbers)
+	}
+
+	fn cell<C: Cell>(&mut self) -> Result<C, RuntimeError> {
+		let value = self.size()?;
+		match C::ZERO.add_count(value) {
+			(cell, 0) => Ok(cell),
+			_ => Err(RuntimeError::InvalidEncoding) // too wide for the cell type
+		}
+	}
+
+	fn error(&mut self) -> Result<RuntimeError, RuntimeError> {
+		let index = self.size()?;
+		#[cfg(feature = "std")]
+		if index == FIXED_ERRORS.len() {
+			return IO_KINDS.get(self.size()?).map(|&kind| RuntimeError::Io(kind)).ok_or(RuntimeError::InvalidEncoding);
+		}
+		if index == FIXED_ERRORS.len() + 1 {
+			return Ok(RuntimeError::NonAsciiInput(self.byte()?, self.size()?));
+		}
+		if index == FIXED_ERRORS.len() + 2 {
+			return Ok(RuntimeError::LoopLimitExceeded(self.size()?));
+		}
+		FIXED_ERRORS.get(index).cloned().ok_or(RuntimeError::InvalidEncoding)
+	}
+
+}
+
+impl<C: Cell> RuntimeProduct<C> {
+
+	// the product, snapshots included, in a compact binary form that from_bytes() reads back
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut encoder = Encoder { bytes: MAGIC.to_vec() };
+		encoder.bytes.push(VERSION);
+		encoder.bytes.push(mem::size_of::<C>() as u8);
+
+		encoder.size(self.executions);
+		encoder.number(self.time);
+		encoder.blob(&self.output);
+		encoder.blob(&self.input);
+
+		let mut messages: Vec<&'static str> = Vec::new();
+		for snapshot in &self.snapshots {
+			if !messages.contains(&snapshot.message) {
+				messages.push(snapshot.message);
+			}
+		}
+		encoder.size(messages.len());
+		for message in &messages {
+			encoder.blob(message.as_bytes());
+		}
+
+		encoder.size(self.snapshots.len());
+		for snapshot in &self.snapshots {
+			encoder.size(snapshot.memory.len());
+			for &cell in &snapshot.memory {
+				encoder.size(cell.to_count());
+			}
+			encoder.size(snapshot.memory_pointer);
+			encoder.size(snapshot.origin);
+			encoder.size(snapshot.instruction_pointer);
+			encoder.size(snapshot.resume_pointer);
+			encoder.size(snapshot.input_pointer);
+			encoder.blob(&snapshot.output);
+			encoder.flag(snapshot.is_error);
+			encoder.size(messages.iter().position(|&message| message == snapshot.message).unwrap_or(0));
+		}
+
+		encoder.flag(self.error.is_some());
+		if let Some(error) = self.error {
+			encoder.error(error);
+		}
+		match self.termination {
+			Termination::Completed => encoder.size(0),
+			Termination::ExecutionLimit => encoder.size(1),
+			Termination::TimeLimit => encoder.size(2),
+			Termination::Error(error) => {
+				encoder.size(3);
+				encoder.error(error);
+			},
+			Termination::Aborted => encoder.size(4)
+		}
+		encoder.size(self.instruction_pointer);
+
+		encoder.size(self.peak_memory);
+		encoder.size(self.allocated_memory);
+
+		encoder.flag(self.profile.is_some());
+		if let Some(profile) = self.profile {
+			for &count in &[profile.right, profile.left, profile.increment, profile.decrement, profile.output, profile.input, profile.open, profile.close, profile.clears, profile.multiplies] {
+				encoder.size(count);
+			}
+		}
+		encoder.flag(self.value_histogram.is_some());
+		if let Some(ref histogram) = self.value_histogram {
+			encoder.numbers(histogram);
+		}
+		encoder.flag(self.loop_iterations.is_some());
+		if let Some(ref loops) = self.loop_iterations {
+			encoder.size(loops.len());
+			for (&offset, &count) in loops {
+				encoder.size(offset);
+				encoder.number(count);
+			}
+		}
+		encoder.flag(self.wraps.is_some());
+		if let Some(wraps) = self.wraps {
+			encoder.size(wraps);
+		}
+		encoder.flag(self.touched.is_some());
+		if let Some(ref touched) = self.touched {
+			encoder.numbers(touched);
+		}
+		encoder.flag(self.instruction_times.is_some());
+		if let Some(ref times) = self.instruction_times {
+			encoder.numbers(times);
+		}
+		encoder.size(self.scanned_instructions);
+
+		encoder.bytes
+	}
+
+	// read back a product written by to_bytes() for the same cell type; anything truncated, malformed
+	// or left over, including a snapshot message this engine never produces, is an InvalidEncoding error
+	pub fn from_bytes(bytes: &[u8]) -> Result<RuntimeProduct<C>, RuntimeError> {
+		let header = [MAGIC, &[VERSION, mem::size_of::<C>() as u8]].concat();
+		if !bytes.starts_with(&header) {
+			return Err(RuntimeError::InvalidEncoding);
+		}
+		let mut decoder = Decoder { bytes, position: header.len() };
+
+		let executions = decoder.size()?;
+		let time = decoder.number()?;
+		let output = decoder.blob()?.to_vec();
+		let input = decoder.blob()?.to_vec();
+
+		let known = known_messages::<C>();
+		let mut messages = Vec::new();
+		for _ in 0..decoder.length()? {
+			let message = decoder.blob()?;
+			messages.push(*known.iter().find(|known| known.as_bytes() == message).ok_or(RuntimeError::InvalidEncoding)?);
+		}
+
+		let mut snapshots = Vec::new();
+		for _ in 0..decoder.length()? {
+			let mut memory = Vec::new();
+			for _ in 0..decoder.length()? {
+				memory.push(decoder.cell()?);
+			}
+
+			snapshots.push(RuntimeSnapshot {
+				memory,
+				memory_pointer: decoder.size()?,
+				origin: decoder.size()?,
+				instruction_pointer: decoder.size()?,
+				resume_pointer: decoder.size()?,
+				input_pointer: decoder.size()?,
+				output: decoder.blob()?.to_vec(),
+
+				is_error: decoder.flag()?,
+				message: messages.get(decoder.size()?).cloned().ok_or(RuntimeError::InvalidEncoding)?
+			});
+		}
+
+		let error = if decoder.flag()? { Some(decoder.error()?) } else { None };
+		let termination = match decoder.size()? {
+			0 => Termination::Completed,
+			1 => Termination::ExecutionLimit,
+			2 => Termination::TimeLimit,
+			3 => Termination::Error(decoder.error()?),
+			4 => Termination::Aborted,
+			_ => return Err(RuntimeError::InvalidEncoding)
+		};
+		let instruction_pointer = decoder.size()?;
+
+		let peak_memory = decoder.size()?;
+		let allocated_memory = decoder.size()?;
+
+		let profile = if decoder.flag()? {
+			Some(InstructionProfile {
+				right: decoder.size()?,
+				left: decoder.size()?,
+				increment: decoder.size()?,
+				decrement: decoder.size()?,
+				output: decoder.size()?,
+				input: decoder.size()?,
+				open: decoder.size()?,
+				close: decoder.size()?,
+
+				clears: decoder.size()?,
+				multiplies: decoder.size()?
+			})
+		} else {
+			None
+		};
+		let value_histogram = if decoder.flag()? { Some(decoder.numbers()?) } else { None };
+		let loop_iterations = if decoder.flag()? {
+			let mut loops = BTreeMap::new();
+			for _ in 0..decoder.length()? {
+				loops.insert(decoder.size()?, decoder.number()?);
+			}
+			Some(loops)
+		} else {
+			None
+		};
+		let wraps = if decoder.flag()? { Some(decoder.size()?) } else { None };
+		let touched = if decoder.flag()? { Some(decoder.numbers()?) } else { None };
+		let instruction_times = if decoder.flag()? { Some(decoder.numbers()?) } else { None };
+		let scanned_instructions = decoder.size()?;
+
+		if decoder.position != bytes.len() {
+			return Err(RuntimeError::InvalidEncoding);
+		}
+
+		Ok(RuntimeProduct {
+			executions,
+			time,
+			output,
+			input,
+			snapshots,
+
+			error,
+			termination,
+			instruction_pointer,
+
+			peak_memory,
+			allocated_memory,
+
+			profile,
+			value_histogram,
+			loop_iterations,
+			wraps,
+			touched,
+			instruction_times,
+
+			scanned_instructions
+		})
+	}
+
+}
@@ -0,0 +1,434 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::sync::atomic::AtomicBool;
+#[cfg(feature = "std")]
+use std::io::{Read, Write};
+
+use input::InputSource;
+use {Cell, Clock, EofBehavior, GrowthStrategy, InstructionSet, OutputMode, RecoveryMode, Runtime, RuntimeError, TapeMode};
+
+// chainable alternative to the positional constructors; anything left unset is unlimited or defaulted
+pub struct RuntimeBuilder<C: Cell = u8> {
+	instructions: String,
+	input: InputSource,
+	input_streams: Vec<Vec<u8>>,
+	#[cfg(feature = "std")]
+	output: Option<Box<dyn Write + Send>>,
+	#[cfg(feature = "std")]
+	tee: Option<Box<dyn Write + Send>>,
+	clock: Option<Box<dyn Clock>>,
+	cancel_token: Option<Arc<AtomicBool>>,
+	cancel_check_interval: usize,
+
+	execution_limit: usize,
+	memory_limit: usize,
+	output_limit: usize,
+	output_mode: OutputMode,
+	time_limit_ns: u64,
+	total_memory_limit: usize,
+	loop_iteration_limit: usize,
+	max_program_len: usize,
+	max_instructions: usize,
+	initial_memory: usize,
+	fill_value: u8,
+	initial_tape: Vec<C>,
+
+	eof_behavior: EofBehavior,
+	validate_ascii_input: bool,
+	tape_mode: TapeMode,
+	grow_left: bool,
+	growth_strategy: GrowthStrategy,
+	signed_cells: bool,
+	recovery_modes: Vec<(RuntimeError, RecoveryMode)>,
+	record_snapshots: bool,
+	profile: bool,
+	track_wraps: bool,
+	track_touched: bool,
+	time_instructions: bool,
+	instruction_set: InstructionSet,
+	debug_dump: bool,
+	zero_cell: bool,
+	warnings_as_errors: bool,
+	strict_mode: bool,
+	error_dump: bool,
+	optimize: bool,
+
+	cell: PhantomData<C>
+}
+
+impl RuntimeBuilder {
+
+	pub fn new() -> RuntimeBuilder {
+		RuntimeBuilder::default()
+	}
+
+}
+
+impl<C: Cell> Default for RuntimeBuilder<C> {
+	fn default() -> RuntimeBuilder<C> {
+		RuntimeBuilder {
+			instructions: String::new(),
+			input: InputSource::Buffer(Vec::new()),
+			input_streams: Vec::new(),
+			#[cfg(feature = "std")]
+			output: None,
+			#[cfg(feature = "std")]
+			tee: None,
+			clock: None,
+			cancel_token: None,
+			cancel_check_interval: 1024,
+
+			execution_limit: 0,
+			memory_limit: 0,
+			output_limit: 0,
+			output_mode: OutputMode::Buffered,
+			time_limit_ns: 0,
+			total_memory_limit: 0,
+			loop_iteration_limit: 0,
+			max_program_len: 0,
+			max_instructions: 0,
+			initial_memory: 1,
+			fill_value: 0,
+			initial_tape: Vec::new(),
+
+			eof_behavior: EofBehavior::MaxValue,
+			validate_ascii_input: false,
+			tape_mode: TapeMode::Growable,
+			grow_left: false,
+			growth_strategy: GrowthStrategy::default(),
+			signed_cells: false,
+			recovery_modes: Vec::new(),
+			record_snapshots: true,
+			profile: false,
+			track_wraps: false,
+			track_touched: false,
+			time_instructions: false,
+			instruction_set: InstructionSet::standard(),
+			debug_dump: false,
+			zero_cell: false,
+			warnings_as_errors: false,
+			strict_mode: false,
+			error_dump: false,
+			optimize: false,
+
+			cell: PhantomData
+		}
+	}
+}
+
+impl<C: Cell> RuntimeBuilder<C> {
+
+	pub fn instructions(mut self, instructions: String) -> RuntimeBuilder<C> {
+		self.instructions = instructions;
+		self
+	}
+
+	pub fn input(mut self, input: Vec<u8>) -> RuntimeBuilder<C> {
+		self.input = InputSource::Buffer(input);
+		self
+	}
+
+	// input as text; each char is fed as its UTF-8 bytes, so anything outside ASCII takes several ','
+	pub fn input_str(self, input: &str) -> RuntimeBuilder<C> {
+		self.input(input.as_bytes().to_vec())
+	}
+
+	// pull ',' input lazily from source instead of a preloaded buffer
+	#[cfg(feature = "std")]
+	pub fn input_reader<R: Read + Send + 'static>(mut self, source: R) -> RuntimeBuilder<C> {
+		self.input = InputSource::Reader(Box::new(source));
+		self
+	}
+
+	// ask callback for each ',' byte on demand; None means end of input
+	pub fn input_callback<F: FnMut() -> Option<u8> + Send + 'static>(mut self, callback: F) -> RuntimeBuilder<C> {
+		self.input = InputSource::Callback(Box::new(callback));
+		self
+	}
+
+	// len pseudo-random input bytes, reproducible from the seed
+	pub fn random_input(mut self, seed: u64, len: usize) -> RuntimeBuilder<C> {
+		self.input = InputSource::Random { seed, len };
+		self
+	}
+
+	// another buffered input stream after the main one; ';' cycles ',' through them in the order they were added
+	pub fn input_stream(mut self, input: Vec<u8>) -> RuntimeBuilder<C> {
+		self.input_streams.push(input);
+		self
+	}
+
+	// stream '.' output to sink instead of buffering it
+	#[cfg(feature = "std")]
+	pub fn output<W: Write + Send + 'static>(mut self, sink: W) -> RuntimeBuilder<C> {
+		self.output = Some(Box::new(sink));
+		self
+	}
+
+	// also mirror output to tee as it is produced; errors writing to it are not fatal
+	#[cfg(feature = "std")]
+	pub fn tee_output<W: Write + Send + 'static>(mut self, tee: W) -> RuntimeBuilder<C> {
+		self.tee = Some(Box::new(tee));
+		self
+	}
+
+	// time runs with clock instead of the system clock
+	pub fn clock<K: Clock + 'static>(mut self, clock: K) -> RuntimeBuilder<C> {
+		self.clock = Some(Box::new(clock));
+		self
+	}
+
+	// stop runs with an Aborted termination once token is set, checked every cancel_check_interval instructions
+	pub fn cancel_token(mut self, token: Arc<AtomicBool>) -> RuntimeBuilder<C> {
+		self.cancel_token = Some(token);
+		self
+	}
+
+	pub fn cancel_check_interval(mut self, cancel_check_interval: usize) -> RuntimeBuilder<C> {
+		self.cancel_check_interval = cancel_check_interval;
+		self
+	}
+
+	pub fn execution_limit(mut self, execution_limit: usize) -> RuntimeBuilder<C> {
+		self.execution_limit = execution_limit;
+		self
+	}
+
+	pub fn memory_limit(mut self, memory_limit: usize) -> RuntimeBuilder<C> {
+		self.memory_limit = memory_limit;
+		self
+	}
+
+	// total bytes '.' may emit, whether buffered or streamed
+	pub fn output_limit(mut self, output_limit: usize) -> RuntimeBuilder<C> {
+		self.output_limit = output_limit;
+		self
+	}
+
+	// OutputMode::Ring(n) keeps only the last n bytes of buffered output
+	pub fn output_mode(mut self, output_mode: OutputMode) -> RuntimeBuilder<C> {
+		self.output_mode = output_mode;
+		self
+	}
+
+	// wall-clock budget in nanoseconds
+	pub fn time_limit_ns(mut self, time_limit_ns: u64) -> RuntimeBuilder<C> {
+		self.time_limit_ns = time_limit_ns;
+		self
+	}
+
+	// bytes the tape, output and snapshots may hold between them; the knob for keeping any one program from exhausting memory
+	pub fn total_memory_limit(mut self, total_memory_limit: usize) -> RuntimeBuilder<C> {
+		self.total_memory_limit = total_memory_limit;
+		self
+	}
+
+	// passes any one loop's body may make each time it is entered; unlike the execution limit, exceeding it is an error
+	pub fn loop_iteration_limit(mut self, loop_iteration_limit: usize) -> RuntimeBuilder<C> {
+		self.loop_iteration_limit = loop_iteration_limit;
+		self
+	}
+
+	// source bytes, comments included, past which the program is rejected unrun; a guard for untrusted programs
+	pub fn max_program_len(mut self, max_program_len: usize) -> RuntimeBuilder<C> {
+		self.max_program_len = max_program_len;
+		self
+	}
+
+	// instructions, comments excluded, past which the program is rejected unrun
+	pub fn max_instructions(mut self, max_instructions: usize) -> RuntimeBuilder<C> {
+		self.max_instructions = max_instructions;
+		self
+	}
+
+	// number of cells allocated up front, before the pointer ever has to grow the tape
+	pub fn initial_memory(mut self, initial_memory: usize) -> RuntimeBuilder<C> {
+		self.initial_memory = initial_memory;
+		self
+	}
+
+	// the value fresh cells hold, initial and grown alike, in place of zero
+	pub fn fill_value(mut self, fill_value: u8) -> RuntimeBuilder<C> {
+		self.fill_value = fill_value;
+		self
+	}
+
+	// cells preloaded onto the tape from the origin on, e.g. the data a routine under test works on
+	pub fn initial_tape(mut self, initial_tape: Vec<C>) -> RuntimeBuilder<C> {
+		self.initial_tape = initial_tape;
+		self
+	}
+
+	pub fn eof_behavior(mut self, eof_behavior: EofBehavior) -> RuntimeBuilder<C> {
+		self.eof_behavior = eof_behavior;
+		self
+	}
+
+	// store eof_value once input runs out; shorthand for eof_behavior(EofBehavior::Value(eof_value))
+	pub fn eof_value(self, eof_value: u8) -> RuntimeBuilder<C> {
+		self.eof_behavior(EofBehavior::Value(eof_value))
+	}
+
+	// see Runtime::set_validate_ascii_input(); off by default, since binary input is legitimate for many programs
+	pub fn validate_ascii_input(mut self, validate_ascii_input: bool) -> RuntimeBuilder<C> {
+		self.validate_ascii_input = validate_ascii_input;
+		self
+	}
+
+	// a fixed tape mode overrides initial_memory with its own length
+	pub fn tape_mode(mut self, tape_mode: TapeMode) -> RuntimeBuilder<C> {
+		self.tape_mode = tape_mode;
+		self
+	}
+
+	// let '<' past the first cell of a growable tape prepend cells, renumbering the rest, instead of underflowing
+	pub fn grow_left(mut self, grow_left: bool) -> RuntimeBuilder<C> {
+		self.grow_left = grow_left;
+		self
+	}
+
+	// how many cells the tape grows by each time the pointer runs off it
+	pub fn growth_strategy(mut self, growth_strategy: GrowthStrategy) -> RuntimeBuilder<C> {
+		self.growth_strategy = growth_strategy;
+		self
+	}
+
+	// wrap +- at the two's complement boundary, e.g. 127 to -128 for bytes
+	pub fn signed_cells(mut self, signed_cells: bool) -> RuntimeBuilder<C> {
+		self.signed_cells = signed_cells;
+		self
+	}
+
+	// make error fatal or recoverable; see Runtime::set_recovery_mode for which errors can be recovered from
+	pub fn recovery_mode(mut self, error: RuntimeError, mode: RecoveryMode) -> RuntimeBuilder<C> {
+		self.recovery_modes.push((error, mode));
+		self
+	}
+
+	// disable to skip per-instruction snapshots entirely, for long-running programs
+	pub fn record_snapshots(mut self, record_snapshots: bool) -> RuntimeBuilder<C> {
+		self.record_snapshots = record_snapshots;
+		self
+	}
+
+	// count executed instructions by kind and written cell values, reported in the product
+	pub fn profile(mut self, profile: bool) -> RuntimeBuilder<C> {
+		self.profile = profile;
+		self
+	}
+
+	// count cell wraparounds, reported in the product
+	pub fn track_wraps(mut self, track_wraps: bool) -> RuntimeBuilder<C> {
+		self.track_wraps = track_wraps;
+		self
+	}
+
+	// mark each cell written, for RuntimeProduct::touched_cells()
+	pub fn track_touched(mut self, track_touched: bool) -> RuntimeBuilder<C> {
+		self.track_touched = track_touched;
+		self
+	}
+
+	// measure the time spent at each source offset, reported in the product; slow, since it reads the clock twice per instruction
+	pub fn time_instructions(mut self, time_instructions: bool) -> RuntimeBuilder<C> {
+		self.time_instructions = time_instructions;
+		self
+	}
+
+	// the tokens the source spells each operation with, for dialects such as Ook!
+	pub fn instruction_set(mut self, instruction_set: InstructionSet) -> RuntimeBuilder<C> {
+		self.instruction_set = instruction_set;
+		self
+	}
+
+	// treat '#' as a request for a snapshot, kept even when snapshot recording is off
+	pub fn debug_dump(mut self, debug_dump: bool) -> RuntimeBuilder<C> {
+		self.debug_dump = debug_dump;
+		self
+	}
+
+	// treat '@' as zeroing the current cell
+	pub fn zero_cell(mut self, zero_cell: bool) -> RuntimeBuilder<C> {
+		self.zero_cell = zero_cell;
+		self
+	}
+
+	// refuse to run a program with anything Runtime::analyze() warns about, failing at the first warning's offset
+	pub fn warnings_as_errors(mut self, warnings_as_errors: bool) -> RuntimeBuilder<C> {
+		self.warnings_as_errors = warnings_as_errors;
+		self
+	}
+
+	// refuse to run a program with anything but instructions and whitespace in it
+	pub fn strict_mode(mut self, strict_mode: bool) -> RuntimeBuilder<C> {
+		self.strict_mode = strict_mode;
+		self
+	}
+
+	// snapshot the whole tape on a fatal error, even with snapshots off
+	pub fn error_dump(mut self, error_dump: bool) -> RuntimeBuilder<C> {
+		self.error_dump = error_dump;
+		self
+	}
+
+	// fold runs of identical instructions into single ops; leave off to step through the source one instruction at a time
+	pub fn optimize(mut self, optimize: bool) -> RuntimeBuilder<C> {
+		self.optimize = optimize;
+		self
+	}
+
+	pub fn build(self) -> Runtime<C> {
+		let mut runtime = Runtime::with_cell_size(self.instructions, Vec::new(), self.execution_limit, self.memory_limit);
+		runtime.input = self.input;
+		for input in self.input_streams {
+			runtime.add_input_stream(input);
+		}
+
+		runtime.set_fill_value(self.fill_value);
+		runtime.set_initial_memory(self.initial_memory);
+		runtime.set_initial_tape(self.initial_tape);
+		#[cfg(feature = "std")]
+		{
+			runtime.output_sink = self.output;
+			runtime.output_tee = self.tee;
+		}
+		if let Some(clock) = self.clock {
+			runtime.clock = clock;
+		}
+		runtime.cancel_token = self.cancel_token;
+		runtime.set_cancel_check_interval(self.cancel_check_interval);
+		runtime.set_output_limit(self.output_limit);
+		runtime.set_output_mode(self.output_mode);
+		runtime.set_time_limit_ns(self.time_limit_ns);
+		runtime.set_total_memory_limit(self.total_memory_limit);
+		runtime.set_loop_iteration_limit(self.loop_iteration_limit);
+		runtime.set_max_program_len(self.max_program_len);
+		runtime.set_max_instructions(self.max_instructions);
+		runtime.set_eof_behavior(self.eof_behavior);
+		runtime.set_validate_ascii_input(self.validate_ascii_input);
+		runtime.set_tape_mode(self.tape_mode);
+		runtime.set_grow_left(self.grow_left);
+		runtime.set_growth_strategy(self.growth_strategy);
+		runtime.set_signed_cells(self.signed_cells);
+		for (error, mode) in self.recovery_modes {
+			runtime.set_recovery_mode(error, mode);
+		}
+		runtime.set_record_snapshots(self.record_snapshots);
+		runtime.set_profile(self.profile);
+		runtime.set_track_wraps(self.track_wraps);
+		runtime.set_track_touched(self.track_touched);
+		runtime.set_time_instructions(self.time_instructions);
+		runtime.set_instruction_set(self.instruction_set);
+		runtime.set_debug_dump(self.debug_dump);
+		runtime.set_zero_cell(self.zero_cell);
+		runtime.set_warnings_as_errors(self.warnings_as_errors);
+		runtime.set_strict_mode(self.strict_mode);
+		runtime.set_error_dump(self.error_dump);
+		runtime.set_optimize(self.optimize);
+		runtime
+	}
+
+}
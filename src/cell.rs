@@ -0,0 +1,78 @@
+use core::fmt;
+
+// a single tape cell; implemented for the unsigned integer widths used by common Brainfuck dialects
+pub trait Cell: Copy + PartialEq + PartialOrd + Default + fmt::Debug + fmt::Display + fmt::UpperHex {
+	const ZERO: Self;
+	const MAX: Self;
+
+	// snapshot messages for the two wrapping cases, spelled out per width
+	const OVERFLOW_MESSAGE: &'static str;
+	const UNDERFLOW_MESSAGE: &'static str;
+	const SIGNED_OVERFLOW_MESSAGE: &'static str;
+	const SIGNED_UNDERFLOW_MESSAGE: &'static str;
+
+	// add or subtract count with wrapping, also returning how many times the value wrapped around
+	fn add_count(self, count: usize) -> (Self, usize);
+	fn sub_count(self, count: usize) -> (Self, usize);
+
+	fn from_byte(byte: u8) -> Self;
+	fn to_byte(self) -> u8; // truncates to the low byte, which is what '.' emits
+	fn to_count(self) -> usize; // the unsigned value, as the iteration count of a loop stepping it down to zero
+
+	// toggle the top bit, mapping two's complement onto offset binary and back, so signed wraps can be counted with add_count()
+	fn flip_sign(self) -> Self;
+}
+
+macro_rules! impl_cell {
+	($cell:ty, $overflow:expr, $underflow:expr, $signed_overflow:expr, $signed_underflow:expr) => {
+		impl Cell for $cell {
+			const ZERO: $cell = 0;
+			const MAX: $cell = <$cell>::MAX;
+
+			const OVERFLOW_MESSAGE: &'static str = $overflow;
+			const UNDERFLOW_MESSAGE: &'static str = $underflow;
+			const SIGNED_OVERFLOW_MESSAGE: &'static str = $signed_overflow;
+			const SIGNED_UNDERFLOW_MESSAGE: &'static str = $signed_underflow;
+
+			fn add_count(self, count: usize) -> ($cell, usize) {
+				let modulus = <$cell>::MAX as u128 + 1;
+				let total = self as u128 + count as u128;
+				((total % modulus) as $cell, (total / modulus) as usize)
+			}
+
+			fn sub_count(self, count: usize) -> ($cell, usize) {
+				let modulus = <$cell>::MAX as u128 + 1;
+				let (value, count) = (self as u128, count as u128);
+				if count <= value {
+					return ((value - count) as $cell, 0);
+				}
+
+				let wraps = (count - value).div_ceil(modulus);
+				((value + (wraps * modulus) - count) as $cell, wraps as usize)
+			}
+
+			fn from_byte(byte: u8) -> $cell {
+				byte as $cell
+			}
+
+			fn to_byte(self) -> u8 {
+				self as u8
+			}
+
+			fn to_count(self) -> usize {
+				self as usize
+			}
+
+			fn flip_sign(self) -> $cell {
+				self ^ !(<$cell>::MAX >> 1)
+			}
+		}
+	}
+}
+
+impl_cell!(u8, "wrapped overflow byte back to 0x00", "wrapped overflow byte back to 0xFF",
+	"wrapped signed overflow byte back to -128", "wrapped signed underflow byte back to 127");
+impl_cell!(u16, "wrapped overflow cell back to 0x0000", "wrapped overflow cell back to 0xFFFF",
+	"wrapped signed overflow cell back to -32768", "wrapped signed underflow cell back to 32767");
+impl_cell!(u32, "wrapped overflow cell back to 0x00000000", "wrapped overflow cell back to 0xFFFFFFFF",
+	"wrapped signed overflow cell back to -2147483648", "wrapped signed underflow cell back to 2147483647");
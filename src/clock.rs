@@ -0,0 +1,73 @@
+use alloc::boxed::Box;
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+// where a run reads the time from, both for the elapsed time in its product and for the time limit
+pub trait Clock: Send {
+	// nanoseconds since an arbitrary fixed point; must never go backwards
+	fn now_ns(&mut self) -> u64;
+}
+
+// the system's monotonic clock, counting from when it was created; used unless another is set
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug)]
+pub struct SystemClock {
+	epoch: Instant
+}
+
+#[cfg(feature = "std")]
+impl SystemClock {
+
+	pub fn new() -> SystemClock {
+		SystemClock { epoch: Instant::now() }
+	}
+
+}
+
+#[cfg(feature = "std")]
+impl Default for SystemClock {
+	fn default() -> SystemClock {
+		SystemClock::new()
+	}
+}
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+	fn now_ns(&mut self) -> u64 {
+		self.epoch.elapsed().as_nanos() as u64 // overflows only after some 584 years
+	}
+}
+
+// a clock for deterministic tests: starts at start and moves on by step nanoseconds every time it is read
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MockClock {
+	pub now: u64,
+	pub step: u64
+}
+
+impl MockClock {
+
+	pub fn new(start: u64, step: u64) -> MockClock {
+		MockClock { now: start, step }
+	}
+
+}
+
+impl Clock for MockClock {
+	fn now_ns(&mut self) -> u64 {
+		let now = self.now;
+		self.now += self.step;
+		now
+	}
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn default_clock() -> Box<dyn Clock> {
+	Box::new(SystemClock::new())
+}
+
+// without std there is no clock to read, so every time is zero and a time limit never fires until one is set
+#[cfg(not(feature = "std"))]
+pub(crate) fn default_clock() -> Box<dyn Clock> {
+	Box::new(MockClock::new(0, 0))
+}
@@ -0,0 +1,205 @@
+use alloc::vec::Vec;
+
+use {InstructionSet, RuntimeError};
+
+// one executable operation; counts are how many source instructions were folded into it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+	Right(usize),
+	Left(usize),
+	Add(usize),
+	Sub(usize),
+	Output,
+	Input,
+	Open(usize), // index of the matching Close
+	Close(usize), // index of the matching Open
+	Clear(bool), // a [-] or, when true, [+] loop, which always leaves the cell at zero
+	Multiply(usize), // a fused multiply/move loop, whose targets are the next this many ops, then the loop unfused; also clears the counter
+	Target(isize, isize), // offset from the counter and the multiple of it added there; only read by Multiply, never executed
+	DebugDump, // '#', which only asks for a snapshot of the current state
+	SwitchInput, // ';', which makes the next input stream the active one
+	Zero // '@', which sets the current cell to zero outright
+}
+
+// an op along with the source offset of the first instruction it was compiled from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Instruction {
+	pub op: Op,
+	pub offset: usize
+}
+
+// one recognized instruction in the source, spelled however the dialect spells it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Token {
+	pub offset: usize,
+	pub length: usize, // in bytes; 1 for every standard instruction
+	pub symbol: u8 // the standard instruction this token stands for
+}
+
+// split the source into instruction tokens, skipping comments; extension tokens are only recognized when their
+// symbol is among extensions
+pub fn tokenize(instructions: &[u8], set: &InstructionSet, extensions: &[u8]) -> Vec<Token> {
+	let symbols = set.symbols(extensions);
+	let mut tokens = Vec::new();
+
+	let mut offset = 0;
+	while offset < instructions.len() {
+		let rest = &instructions[offset..];
+		match symbols.iter().find(|&&(token, _)| rest.starts_with(token)) {
+			Some(&(token, symbol)) => {
+				tokens.push(Token { offset, length: token.len(), symbol });
+				offset += token.len();
+			},
+			None => offset += 1 // comment
+		}
+	}
+
+	tokens
+}
+
+// translate source tokens into ops, resolving every bracket pair up front;
+// with optimize, consecutive identical +-<> collapse into one op and clear loops become a single op,
+// but never across one of the (sorted) boundaries, so execution can stop or resume at any of them;
+// multiply loops are fused too when fuse_multiply is set, which is only sound while pointer moves are exact, and
+// no loop is fused at all without fuse_loops, for when every pass of a loop has to be counted
+pub fn compile(instructions: &[u8], set: &InstructionSet, extensions: &[u8], optimize: bool, fuse_loops: bool, fuse_multiply: bool, boundaries: &[usize]) -> Result<Vec<Instruction>, (usize, RuntimeError)> {
+	let mut program: Vec<Instruction> = Vec::new();
+	let mut open_brackets: Vec<usize> = Vec::new();
+
+	for token in tokenize(instructions, set, extensions) {
+		let offset = token.offset;
+		let op = match token.symbol {
+			b'>' => Op::Right(1),
+			b'<' => Op::Left(1),
+			b'+' => Op::Add(1),
+			b'-' => Op::Sub(1),
+			b'.' => Op::Output,
+			b',' => Op::Input,
+			b'[' => {
+				open_brackets.push(program.len());
+				Op::Open(0) // patched once the matching close bracket is found
+			},
+			b']' => {
+				match open_brackets.pop() {
+					Some(open_index) => {
+						let open_offset = program[open_index].offset;
+						if optimize && fuse_loops && !spans_boundary(boundaries, open_offset, offset) {
+							if is_clear_loop(&program[(open_index + 1)..]) {
+								let increment = program[open_index + 1].op == Op::Add(1);
+								program.truncate(open_index);
+								program.push(Instruction { op: Op::Clear(increment), offset: open_offset });
+								continue;
+							}
+
+							if let Some(targets) = multiply_targets(&program[(open_index + 1)..]).filter(|_| fuse_multiply) {
+								let body = program.split_off(open_index + 1);
+								program.truncate(open_index);
+								program.push(Instruction { op: Op::Multiply(targets.len()), offset: open_offset });
+								program.extend(targets.into_iter().map(|(target, factor)| Instruction { op: Op::Target(target, factor), offset: open_offset }));
+
+								// the loop as written, which Multiply runs instead when its targets don't all fit on the tape
+								let fallback = program.len();
+								program.push(Instruction { op: Op::Open(fallback + body.len() + 1), offset: open_offset });
+								program.extend(body);
+								program.push(Instruction { op: Op::Close(fallback), offset });
+								continue;
+							}
+						}
+
+						program[open_index].op = Op::Open(program.len());
+						Op::Close(open_index)
+					},
+					None => return Err((offset, RuntimeError::UnmatchedBracket))
+				}
+			},
+			b';' => Op::SwitchInput,
+			b'@' => Op::Zero,
+			_ => Op::DebugDump // the only other symbol tokenize() produces
+		};
+
+		if optimize && boundaries.binary_search(&offset).is_err() {
+			if let Some(last) = program.last_mut() {
+				if let Some(folded) = fold(last.op, op) {
+					last.op = folded;
+					continue;
+				}
+			}
+		}
+
+		program.push(Instruction { op, offset });
+	}
+
+	if let Some(&open_index) = open_brackets.first() { // the outermost unclosed bracket is the earliest
+		return Err((program[open_index].offset, RuntimeError::UnmatchedBracket));
+	}
+
+	Ok(program)
+}
+
+// whether any boundary falls after start and at or before end
+fn spans_boundary(boundaries: &[usize], start: usize, end: usize) -> bool {
+	let first_after = match boundaries.binary_search(&start) {
+		Ok(index) => index + 1,
+		Err(index) => index
+	};
+
+	boundaries.get(first_after).is_some_and(|&boundary| boundary <= end)
+}
+
+// whether a loop body (everything between the brackets) just steps the current cell by one until it hits zero
+fn is_clear_loop(body: &[Instruction]) -> bool {
+	matches!(body, [Instruction { op: Op::Sub(1), .. }] | [Instruction { op: Op::Add(1), .. }])
+}
+
+// for a loop body that only moves and adds, ends where it started and steps the current cell (the counter) down
+// by exactly one, every other cell it adds to with the multiple of the counter added there, in order of change.
+// Each cell may only get a single run of '+' or of '-', since a cell stepped both ways can wrap partway through
+// the body however little it ends up changing, and a body that passes over cells beyond its targets isn't fused,
+// since only the targets are bounds-checked
+fn multiply_targets(body: &[Instruction]) -> Option<Vec<(isize, isize)>> {
+	let mut position: isize = 0;
+	let (mut lowest, mut highest) = (0, 0);
+	let mut changes: Vec<(isize, isize)> = Vec::new();
+
+	for instruction in body {
+		let delta = match instruction.op {
+			Op::Right(count) => {
+				position += count as isize;
+				highest = highest.max(position);
+				continue;
+			},
+			Op::Left(count) => {
+				position -= count as isize;
+				lowest = lowest.min(position);
+				continue;
+			},
+			Op::Add(count) => count as isize,
+			Op::Sub(count) => -(count as isize),
+			_ => return None // I/O, nested loops, dumps and zeroing all need the loop to really run
+		};
+
+		if changes.iter().any(|change| change.0 == position) {
+			return None;
+		}
+		changes.push((position, delta));
+	}
+
+	if (position != 0) || !changes.contains(&(0, -1)) {
+		return None;
+	}
+	if !changes.iter().any(|change| change.0 == lowest) || !changes.iter().any(|change| change.0 == highest) {
+		return None; // the loop would fail or grow the tape at a cell it only passes over
+	}
+
+	Some(changes.into_iter().filter(|&(target, _)| target != 0).collect())
+}
+
+fn fold(previous: Op, next: Op) -> Option<Op> {
+	match (previous, next) {
+		(Op::Right(previous), Op::Right(next)) => Some(Op::Right(previous + next)),
+		(Op::Left(previous), Op::Left(next)) => Some(Op::Left(previous + next)),
+		(Op::Add(previous), Op::Add(next)) => Some(Op::Add(previous + next)),
+		(Op::Sub(previous), Op::Sub(next)) => Some(Op::Sub(previous + next)),
+		_ => None
+	}
+}
@@ -0,0 +1,101 @@
+use alloc::vec::Vec;
+
+use {Cell, Runtime, RuntimeError, RuntimeSnapshot};
+
+// why run_until_break() handed control back
+pub enum Pause<C: Cell = u8> {
+	Breakpoint(RuntimeSnapshot<C>), // stopped in front of a breakpoint; the instruction there has not executed yet
+	Watchpoint(usize, RuntimeSnapshot<C>), // a watched cell (by index) was written, with the snapshot of that write
+	Finished(Option<RuntimeSnapshot<C>>) // the program ended or failed, with the last snapshot taken if anything executed
+}
+
+// interactive control over execution, built on step()
+impl<C: Cell> Runtime<C> {
+
+	// stop in front of the instruction at offset, which must be a real instruction rather than a comment
+	pub fn add_breakpoint(&mut self, offset: usize) {
+		if let Err(index) = self.breakpoints.binary_search(&offset) {
+			self.breakpoints.insert(index, offset);
+			self.program = None; // recompiled so no optimized op can span the breakpoint
+		}
+	}
+
+	pub fn remove_breakpoint(&mut self, offset: usize) {
+		if let Ok(index) = self.breakpoints.binary_search(&offset) {
+			self.breakpoints.remove(index);
+			self.program = None;
+		}
+	}
+
+	// pause whenever the cell at index (counted from the origin) changes value
+	pub fn watch_cell(&mut self, index: usize) {
+		self.watchpoints.push((index, None));
+	}
+
+	// pause only when the cell at index is written with value
+	pub fn watch_cell_value(&mut self, index: usize, value: C) {
+		self.watchpoints.push((index, Some(value)));
+	}
+
+	pub fn unwatch_cell(&mut self, index: usize) {
+		self.watchpoints.retain(|&(watched, _)| watched != index);
+	}
+
+	// the first watched cell whose change fires its watchpoint, given every watched cell's value from before the step
+	fn watch_triggered(&self, before: &[C]) -> Option<usize> {
+		self.watchpoints.iter().zip(before).find(|&(&(index, target), &before)| {
+			let after = self.logical_cell(index as isize);
+			match target {
+				Some(value) => (after == value) && (after != before),
+				None => after != before
+			}
+		}).map(|(&(index, _), _)| index)
+	}
+
+	fn at_breakpoint(&self) -> bool {
+		match self.current_instruction() {
+			Some(instruction) => self.breakpoints.binary_search(&instruction.offset).is_ok(),
+			None => false
+		}
+	}
+
+	// execute until the next instruction is at a breakpoint, a watched cell changes, or the program ends;
+	// calling again resumes. The execution limit applies to each call, which finishes with an ExecutionLimitExceeded
+	// snapshot once it is spent, as run() would, without ending the program
+	pub fn run_until_break(&mut self) -> Pause<C> {
+		let mut last_snapshot = None;
+		let mut executions: usize = 0;
+
+		loop {
+			if self.error.is_none() {
+				if let Err((offset, error)) = self.ensure_program() {
+					return Pause::Finished(Some(RuntimeSnapshot::new(self, offset, Err(error))));
+				}
+			}
+
+			if !self.paused && self.at_breakpoint() {
+				self.paused = true;
+				return Pause::Breakpoint(RuntimeSnapshot::new(self, self.instruction_pointer, Ok("paused at breakpoint")));
+			}
+
+			if (self.execution_limit > 0) && (executions >= self.execution_limit) && self.error.is_none() && self.current_instruction().is_some() {
+				return Pause::Finished(Some(RuntimeSnapshot::new(self, self.instruction_pointer, Err(RuntimeError::ExecutionLimitExceeded))));
+			}
+
+			let watched: Vec<C> = self.watchpoints.iter().map(|&(index, _)| self.logical_cell(index as isize)).collect();
+
+			match self.step() {
+				Some(snapshot) => {
+					executions += 1;
+					if let Some(index) = self.watch_triggered(&watched) {
+						return Pause::Watchpoint(index, snapshot);
+					}
+
+					last_snapshot = Some(snapshot);
+				},
+				None => return Pause::Finished(last_snapshot)
+			}
+		}
+	}
+
+}
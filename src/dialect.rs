@@ -0,0 +1,92 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// the tokens a Brainfuck-family dialect spells each operation with; anything else in the source is a comment
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct InstructionSet {
+	pub right: String,
+	pub left: String,
+	pub increment: String,
+	pub decrement: String,
+	pub output: String,
+	pub input: String,
+	pub open: String,
+	pub close: String,
+
+	// extensions, each only recognized while enabled
+	pub debug_dump: String,
+	pub switch_input: String,
+	pub zero: String
+}
+
+impl InstructionSet {
+
+	// > < + - . , [ ] and #
+	pub fn standard() -> InstructionSet {
+		InstructionSet::new(">", "<", "+", "-", ".", ",", "[", "]")
+	}
+
+	// Ook!, where each operation is a pair of words; tokens must be separated by exactly one space inside a pair
+	pub fn ook() -> InstructionSet {
+		InstructionSet::new("Ook. Ook?", "Ook? Ook.", "Ook. Ook.", "Ook! Ook!", "Ook! Ook.", "Ook. Ook!", "Ook! Ook?", "Ook? Ook!")
+	}
+
+	// tokens in the order > < + - . , [ ], with '#' for debug dumps, ';' to switch input and '@' to zero a cell;
+	// an empty token never matches
+	#[allow(clippy::too_many_arguments)]
+	pub fn new(right: &str, left: &str, increment: &str, decrement: &str, output: &str, input: &str, open: &str, close: &str) -> InstructionSet {
+		InstructionSet {
+			right: right.to_string(),
+			left: left.to_string(),
+			increment: increment.to_string(),
+			decrement: decrement.to_string(),
+			output: output.to_string(),
+			input: input.to_string(),
+			open: open.to_string(),
+			close: close.to_string(),
+
+			debug_dump: "#".to_string(),
+			switch_input: ";".to_string(),
+			zero: "@".to_string()
+		}
+	}
+
+	// each token with the standard symbol it stands for, plus the enabled extensions ('#', ';' and '@')
+	pub(crate) fn symbols(&self, extensions: &[u8]) -> Vec<(&[u8], u8)> {
+		let mut symbols = vec![
+			(self.right.as_bytes(), b'>'),
+			(self.left.as_bytes(), b'<'),
+			(self.increment.as_bytes(), b'+'),
+			(self.decrement.as_bytes(), b'-'),
+			(self.output.as_bytes(), b'.'),
+			(self.input.as_bytes(), b','),
+			(self.open.as_bytes(), b'['),
+			(self.close.as_bytes(), b']')
+		];
+
+		for &extension in extensions {
+			match extension {
+				b'#' => symbols.push((self.debug_dump.as_bytes(), b'#')),
+				b';' => symbols.push((self.switch_input.as_bytes(), b';')),
+				b'@' => symbols.push((self.zero.as_bytes(), b'@')),
+				_ => ()
+			}
+		}
+
+		symbols.retain(|&(token, _)| !token.is_empty());
+		symbols.sort_by_key(|&(token, _)| Reverse(token.len())); // longest first, so a token is never cut short by its own prefix
+		symbols
+	}
+
+}
+
+impl Default for InstructionSet {
+	fn default() -> InstructionSet {
+		InstructionSet::standard()
+	}
+}
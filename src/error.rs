@@ -0,0 +1,151 @@
+use core::fmt;
+#[cfg(feature = "std")]
+use std::error::Error;
+#[cfg(feature = "std")]
+use std::io;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+#[cfg(feature = "serde")]
+use serde::de;
+#[cfg(feature = "serde")]
+use alloc::string::String;
+
+// every way a run can fail; all of them are fatal unless a recovery mode says otherwise
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RuntimeError {
+	MemoryLimitExceeded,
+	PointerUnderflow,
+	UnmatchedBracket,
+	ProgramTooLarge, // only raised when a maximum program length or instruction count is set
+	EmptyLoop, // only raised when warnings are treated as errors
+	ExecutionLimitExceeded,
+	TimeLimitExceeded,
+	OutputLimitExceeded,
+	CorruptState, // pointers outside the tape, only detected by Runtime::run_safe()
+	NonAsciiOutput, // only from RuntimeProduct::output_ascii(), never from a run
+	InvalidEncoding, // only from RuntimeProduct::from_bytes(), never from a run
+	UnknownInstruction, // only raised in strict mode
+	TotalMemoryLimitExceeded, // the tape, output and snapshots together, only raised when a total memory limit is set
+	#[cfg(feature = "std")]
+	Io(io::ErrorKind), // reading from or writing to a stream failed
+	NonAsciiInput(u8, usize), // the offending byte and its position in the input, only raised when ASCII input is validated
+	LoopLimitExceeded(usize) // the source offset of the runaway loop's '[', only raised when a loop iteration limit is set
+}
+
+// every error but Io, NonAsciiInput and LoopLimitExceeded, which carry data; new variants go at the end, since the binary encoding numbers them in this order
+pub(crate) const FIXED_ERRORS: [RuntimeError; 13] = [
+	RuntimeError::MemoryLimitExceeded,
+	RuntimeError::PointerUnderflow,
+	RuntimeError::UnmatchedBracket,
+	RuntimeError::ProgramTooLarge,
+	RuntimeError::EmptyLoop,
+	RuntimeError::ExecutionLimitExceeded,
+	RuntimeError::TimeLimitExceeded,
+	RuntimeError::OutputLimitExceeded,
+	RuntimeError::CorruptState,
+	RuntimeError::NonAsciiOutput,
+	RuntimeError::InvalidEncoding,
+	RuntimeError::UnknownInstruction,
+	RuntimeError::TotalMemoryLimitExceeded
+];
+
+impl RuntimeError {
+
+	// the human-readable form stored in snapshot messages
+	pub fn message(&self) -> &'static str {
+		match *self {
+			RuntimeError::MemoryLimitExceeded => "failed to increment pointer (runtime memory limit exceeded)",
+			RuntimeError::PointerUnderflow => "can't decrement pointer sub-0!",
+			RuntimeError::UnmatchedBracket => "found bracket w/o matching partner!",
+			RuntimeError::ProgramTooLarge => "program rejected by engine (program size limit exceeded)",
+			RuntimeError::EmptyLoop => "found empty loop that never ends once entered!",
+			RuntimeError::ExecutionLimitExceeded => "execution terminated by engine (instruction limit exceeded)",
+			RuntimeError::TimeLimitExceeded => "execution terminated by engine (time limit exceeded)",
+			RuntimeError::OutputLimitExceeded => "failed to output byte (runtime output limit exceeded)",
+			RuntimeError::CorruptState => "runtime state is inconsistent (pointer outside memory)",
+			RuntimeError::NonAsciiOutput => "output contains bytes outside 7-bit ASCII",
+			RuntimeError::InvalidEncoding => "encoded product is truncated or malformed",
+			RuntimeError::UnknownInstruction => "found byte that is neither an instruction nor whitespace (strict mode)",
+			RuntimeError::TotalMemoryLimitExceeded => "execution terminated by engine (total memory limit exceeded)",
+			#[cfg(feature = "std")]
+			RuntimeError::Io(_) => "stream I/O failed",
+			RuntimeError::NonAsciiInput(..) => "found input byte that is not printable ASCII",
+			RuntimeError::LoopLimitExceeded(_) => "execution terminated by engine (loop iteration limit exceeded)"
+		}
+	}
+
+}
+
+impl fmt::Display for RuntimeError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			#[cfg(feature = "std")]
+			RuntimeError::Io(kind) => write!(f, "{} ({:?})", self.message(), kind),
+			RuntimeError::NonAsciiInput(byte, position) => write!(f, "{} (0x{:02x} at {})", self.message(), byte, position),
+			RuntimeError::LoopLimitExceeded(offset) => write!(f, "{} (loop at {})", self.message(), offset),
+			_ => f.write_str(self.message())
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl Error for RuntimeError {}
+
+// serialized as its display string, since io::ErrorKind has no serde support
+#[cfg(feature = "serde")]
+impl Serialize for RuntimeError {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.collect_str(self)
+	}
+}
+
+// read back from the display string; the kind of an I/O error is not recovered
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for RuntimeError {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<RuntimeError, D::Error> {
+		let message = String::deserialize(deserializer)?;
+		match FIXED_ERRORS.iter().find(|error| error.message() == message) {
+			Some(&error) => Ok(error),
+			#[cfg(feature = "std")]
+			None if message.starts_with(RuntimeError::Io(io::ErrorKind::Other).message()) => Ok(RuntimeError::Io(io::ErrorKind::Other)),
+			None if message.starts_with(RuntimeError::NonAsciiInput(0, 0).message()) => non_ascii_input(&message).ok_or_else(|| malformed(&message)),
+			None if message.starts_with(RuntimeError::LoopLimitExceeded(0).message()) => loop_limit_exceeded(&message).ok_or_else(|| malformed(&message)),
+			None => Err(de::Error::custom(format!("unknown runtime error {:?}", message)))
+		}
+	}
+}
+
+#[cfg(feature = "serde")]
+fn malformed<E: de::Error>(message: &str) -> E {
+	E::custom(format!("malformed runtime error {:?}", message))
+}
+
+// what display adds in parentheses after an error's message
+#[cfg(feature = "serde")]
+fn details(message: &str, error: RuntimeError) -> Option<&str> {
+	message.strip_prefix(error.message())?.strip_prefix(" (")?.strip_suffix(")")
+}
+
+// the byte and position back out of "... (0x80 at 3)"
+#[cfg(feature = "serde")]
+fn non_ascii_input(message: &str) -> Option<RuntimeError> {
+	let mut details = details(message, RuntimeError::NonAsciiInput(0, 0))?.strip_prefix("0x")?.splitn(2, " at ");
+	let byte = u8::from_str_radix(details.next()?, 16).ok()?;
+	let position = details.next()?.parse().ok()?;
+	Some(RuntimeError::NonAsciiInput(byte, position))
+}
+
+// the offset back out of "... (loop at 3)"
+#[cfg(feature = "serde")]
+fn loop_limit_exceeded(message: &str) -> Option<RuntimeError> {
+	let offset = details(message, RuntimeError::LoopLimitExceeded(0))?.strip_prefix("loop at ")?.parse().ok()?;
+	Some(RuntimeError::LoopLimitExceeded(offset))
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for RuntimeError {
+	fn from(error: io::Error) -> RuntimeError {
+		RuntimeError::Io(error.kind())
+	}
+}
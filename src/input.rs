@@ -0,0 +1,61 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+
+use RuntimeError;
+
+// where ',' pulls its bytes from
+pub enum InputSource {
+	Buffer(Vec<u8>), // preloaded; bytes are indexed by the runtime's input pointer
+	#[cfg(feature = "std")]
+	Reader(Box<dyn Read + Send>), // pulled lazily, one byte per ','
+	Callback(Box<dyn FnMut() -> Option<u8> + Send>), // asked for a byte on demand; None means end of input
+	Random { seed: u64, len: usize } // len pseudo-random bytes, each worked out from the seed and its position
+}
+
+impl InputSource {
+
+	// the byte at position, or None once the source is exhausted
+	pub fn next_byte(&mut self, position: usize) -> Result<Option<u8>, RuntimeError> {
+		match *self {
+			InputSource::Buffer(ref buffer) => Ok(buffer.get(position).cloned()),
+			#[cfg(feature = "std")]
+			InputSource::Reader(ref mut reader) => {
+				let mut byte = [0; 1];
+				loop {
+					match reader.read(&mut byte) {
+						Ok(0) => return Ok(None),
+						Ok(_) => return Ok(Some(byte[0])),
+						Err(ref error) if error.kind() == io::ErrorKind::Interrupted => (),
+						Err(error) => return Err(error.into())
+					}
+				}
+			},
+			InputSource::Callback(ref mut callback) => Ok(callback()),
+			InputSource::Random { seed, len } => Ok(if position < len { Some(random_byte(seed, position)) } else { None })
+		}
+	}
+
+	// whether pulling a byte may block on something outside the runtime
+	pub fn is_stream(&self) -> bool {
+		match *self {
+			InputSource::Buffer(_) | InputSource::Random { .. } => false,
+			_ => true // a reader or a callback
+		}
+	}
+
+}
+
+// splitmix64 of the seed stepped on to position, which any byte can be worked out from without the ones before it
+pub fn random_byte(seed: u64, position: usize) -> u8 {
+	let mut z = seed.wrapping_add((position as u64).wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+	z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+	((z ^ (z >> 31)) >> 56) as u8
+}
+
+// what validated ASCII input accepts: printable characters and the whitespace found in text
+pub fn is_text_byte(byte: u8) -> bool {
+	(byte == b' ') || byte.is_ascii_graphic() || (byte == b'\t') || (byte == b'\n') || (byte == b'\r')
+}
@@ -1,9 +1,69 @@
-extern crate time;
+// without the std feature only alloc is needed, for bare-metal targets
+#![cfg_attr(not(feature = "std"), no_std)]
 
-pub struct RuntimeSnapshot {
-	pub memory: Vec<u8>,
+#[cfg(feature = "std")]
+extern crate core;
+#[cfg_attr(not(feature = "std"), macro_use)]
+extern crate alloc;
+#[cfg(feature = "serde")]
+extern crate serde;
+
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use core::mem;
+use core::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "std")]
+use std::io::{Read, Write};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+mod analysis;
+mod batch;
+mod binary;
+mod builder;
+mod cell;
+mod clock;
+mod compiler;
+mod debugger;
+mod dialect;
+mod error;
+mod input;
+mod profile;
+mod program;
+mod state;
+mod trace;
+
+pub use analysis::{Lint, LintCategory, Warning};
+pub use batch::{run_batch, score_output, BatchLimits, OutputMetric};
+pub use builder::RuntimeBuilder;
+pub use cell::Cell;
+pub use clock::{Clock, MockClock};
+#[cfg(feature = "std")]
+pub use clock::SystemClock;
+pub use debugger::Pause;
+pub use dialect::InstructionSet;
+pub use error::RuntimeError;
+pub use profile::InstructionProfile;
+pub use program::Program;
+pub use state::RuntimeState;
+pub use trace::{Deltas, SnapshotDelta, SnapshotDiff};
+
+use compiler::{Instruction, Op};
+use input::{is_text_byte, InputSource};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct RuntimeSnapshot<C: Cell = u8> {
+	pub memory: Vec<C>,
 	pub memory_pointer: usize,
+	pub origin: usize, // index into memory of logical cell 0, only ever non-zero on a bidirectional tape
 	pub instruction_pointer: usize,
+	pub resume_pointer: usize, // offset execution continues from after this snapshot
 	pub input_pointer: usize,
 	pub output: Vec<u8>,
 
@@ -11,59 +71,418 @@ pub struct RuntimeSnapshot {
 	pub message: &'static str
 }
 
-impl RuntimeSnapshot {
+impl<C: Cell> RuntimeSnapshot<C> {
+
+	// the pointer's position relative to where it started, negative once it has moved left of the origin
+	pub fn logical_pointer(&self) -> isize {
+		self.memory_pointer as isize - self.origin as isize
+	}
+
+	// bytes the snapshot takes up, its buffers included
+	fn footprint(&self) -> usize {
+		mem::size_of::<RuntimeSnapshot<C>>() + (self.memory.capacity() * mem::size_of::<C>()) + self.output.capacity()
+	}
+
+	fn new(runtime: &Runtime<C>, instruction_pointer: usize, result: RuntimeResult) -> RuntimeSnapshot<C> {
+		let memory = match result {
+			Err(_) if runtime.error_dump && runtime.error.is_some() => runtime.memory.clone(), // the whole tape, touched or not
+			_ => runtime.memory[..(runtime.memory_pointer_max + 1)].to_vec()
+		};
 
-	fn new(runtime: &Runtime, memory_pointer_max: usize, is_error: bool, message: &'static str) -> RuntimeSnapshot {
 		RuntimeSnapshot {
-			memory: (&runtime.memory[..(memory_pointer_max + 1)]).to_vec(),
+			memory,
 			memory_pointer: runtime.memory_pointer,
-			instruction_pointer: runtime.instruction_pointer,
+			origin: runtime.origin,
+			instruction_pointer,
+			resume_pointer: runtime.instruction_pointer,
 			input_pointer: runtime.input_pointer,
-			output: runtime.output.to_vec(),
+			output: runtime.output_tail().to_vec(),
 
-			is_error: is_error,
-			message: message
+			is_error: result.is_err(),
+			message: match result {
+				Ok(message) => message,
+				Err(error) => error.message()
+			}
 		}
 	}
 
 }
 
-pub struct RuntimeProduct {
+// why a run stopped
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum Termination {
+	Completed, // ran off the end of the program
+	ExecutionLimit, // refused to go further; a later run() resumes
+	TimeLimit, // likewise
+	Error(RuntimeError),
+	Aborted // a run_with_callback() callback or the cancel token asked to stop
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct RuntimeProduct<C: Cell = u8> {
 	pub executions: usize,
 	pub time: u64,
-	pub output: Vec<u8>,
-	pub snapshots: Vec<RuntimeSnapshot>
+	pub output: Vec<u8>, // only the tail when output is a ring
+	pub input: Vec<u8>, // bytes ',' read since the last reset, in order, whichever stream they came from
+	pub snapshots: Vec<RuntimeSnapshot<C>>,
+
+	pub error: Option<RuntimeError>, // the error that ended the run, if any
+	pub termination: Termination,
+	pub instruction_pointer: usize, // offset execution would continue from, the program's length once it has completed
+
+	pub peak_memory: usize, // highest cell index touched
+	pub allocated_memory: usize, // cells allocated for the tape when the run ended
+
+	pub profile: Option<InstructionProfile>, // only collected when profiling is enabled
+	// likewise, how often each value 0..=255 was written to a cell, wider cells counted by their low byte;
+	// a folded op writes once, so optimized runs record fewer, later values
+	pub value_histogram: Option<Vec<u64>>,
+	// likewise, how many times each loop jumped back from its ']' to run its body again, keyed by the offset of its '[';
+	// loops the optimizer fuses into a single op, such as [-], never jump back, so they are missing
+	pub loop_iterations: Option<BTreeMap<usize, u64>>,
+	pub wraps: Option<usize>, // cell wraparounds since the last reset, only counted when tracking wraps
+	// one bit per cell of the final tape, bit i % 64 of word i / 64 for index i, set for every cell written since the
+	// last reset; only tracked when asked for, and read through touched_cells()
+	pub touched: Option<Vec<u64>>,
+	// nanoseconds spent executing at each source offset since the last reset, one entry per source byte; only
+	// measured when timing instructions, and a folded op's time all goes to the offset it starts at
+	pub instruction_times: Option<Vec<u64>>,
+
+	// source bytes that bracket jumps skipped since the last reset; the jump table crosses them in one
+	// execution, where scanning for the matching bracket would have visited each of them
+	pub scanned_instructions: usize
 }
 
-impl RuntimeProduct {
-	fn new(executions: usize, time: u64, output: Vec<u8>, snapshots: Vec<RuntimeSnapshot>) -> RuntimeProduct {
+impl<C: Cell> RuntimeProduct<C> {
+	fn new(runtime: &Runtime<C>, executions: usize, time: u64, snapshots: Vec<RuntimeSnapshot<C>>, error: Option<RuntimeError>) -> RuntimeProduct<C> {
+		RuntimeProduct {
+			executions,
+			time,
+			output: runtime.output_tail().to_vec(),
+			input: runtime.consumed_input.clone(),
+			snapshots,
+
+			error,
+			termination: match error {
+				Some(RuntimeError::ExecutionLimitExceeded) => Termination::ExecutionLimit,
+				Some(RuntimeError::TimeLimitExceeded) => Termination::TimeLimit,
+				Some(error) => Termination::Error(error),
+				None => Termination::Completed
+			},
+			instruction_pointer: runtime.instruction_pointer,
+
+			peak_memory: runtime.memory_pointer_max,
+			allocated_memory: runtime.memory.capacity(),
+
+			profile: if runtime.profile { Some(runtime.instruction_profile) } else { None },
+			value_histogram: if runtime.profile { Some(runtime.value_histogram.clone()) } else { None },
+			loop_iterations: if runtime.profile { Some(runtime.loop_iterations.clone()) } else { None },
+			wraps: if runtime.track_wraps { Some(runtime.wraps) } else { None },
+			touched: if runtime.track_touched { Some(runtime.touched.clone()) } else { None },
+			instruction_times: if runtime.time_instructions { Some(runtime.instruction_times_padded()) } else { None },
+			scanned_instructions: runtime.scanned_instructions
+		}
+	}
+
+	// take the snapshots, output, executions and time without cloning them
+	pub fn into_parts(self) -> (Vec<RuntimeSnapshot<C>>, Vec<u8>, usize, u64) {
+		(self.snapshots, self.output, self.executions, self.time)
+	}
+
+	// the tape indices of the cells written at all, in order, when touched cells were tracked; comparing how many
+	// there are with peak_memory shows how sparsely a program uses its tape
+	pub fn touched_cells(&self) -> Option<Vec<usize>> {
+		self.touched.as_ref().map(|words| {
+			let bits = words.len() * 64;
+			(0..bits).filter(|&index| words[index / 64] & (1 << (index % 64)) != 0).collect()
+		})
+	}
+
+	// the output as text, with invalid UTF-8 replaced by U+FFFD
+	pub fn output_utf8_lossy(&self) -> String {
+		String::from_utf8_lossy(&self.output).into_owned()
+	}
+
+	// the output as text, refusing anything outside 7-bit ASCII
+	pub fn output_ascii(&self) -> Result<String, RuntimeError> {
+		if !self.output.is_ascii() {
+			return Err(RuntimeError::NonAsciiOutput);
+		}
+
+		Ok(self.output.iter().map(|&byte| byte as char).collect())
+	}
+
+	// combine this stage of a pipeline with the next: outputs and snapshots are appended and every count is summed, while
+	// the error, termination and instruction pointer are the next stage's; peak and allocated memory are the larger of the
+	// two, and instruction times are concatenated like the two sources would be. Statistics only one stage collected are kept as is
+	pub fn merge(self, next: RuntimeProduct<C>) -> RuntimeProduct<C> {
+		let mut output = self.output;
+		output.extend(next.output);
+		let mut input = self.input;
+		input.extend(next.input);
+		let mut snapshots = self.snapshots;
+		snapshots.extend(next.snapshots);
+
 		RuntimeProduct {
-			executions: executions,
-			time: time,
-			output: output,
-			snapshots: snapshots
+			executions: self.executions + next.executions,
+			time: self.time + next.time,
+			output,
+			input,
+			snapshots,
+
+			error: next.error,
+			termination: next.termination,
+			instruction_pointer: next.instruction_pointer,
+
+			peak_memory: self.peak_memory.max(next.peak_memory),
+			allocated_memory: self.allocated_memory.max(next.allocated_memory),
+
+			profile: merge_statistics(self.profile, next.profile, |first, second| first.merge(&second)),
+			value_histogram: merge_statistics(self.value_histogram, next.value_histogram, |first, second| {
+				first.iter().zip(&second).map(|(first, second)| first + second).collect()
+			}),
+			loop_iterations: merge_statistics(self.loop_iterations, next.loop_iterations, |mut first, second| {
+				for (offset, count) in second {
+					*first.entry(offset).or_insert(0) += count; // stages running the same program share offsets
+				}
+				first
+			}),
+			wraps: merge_statistics(self.wraps, next.wraps, |first, second| first + second),
+			touched: merge_statistics(self.touched, next.touched, |mut first, second| {
+				if first.len() < second.len() {
+					first.resize(second.len(), 0);
+				}
+				for (word, other) in first.iter_mut().zip(second) {
+					*word |= other;
+				}
+				first
+			}),
+			instruction_times: merge_statistics(self.instruction_times, next.instruction_times, |mut first, second| {
+				first.extend(second);
+				first
+			}),
+
+			scanned_instructions: self.scanned_instructions + next.scanned_instructions
 		}
 	}
+
+	// the output rendered for display; the raw bytes are the output field itself
+	pub fn formatted_output(&self, format: OutputFormat) -> String {
+		let numbers = || self.output.iter().map(|byte| byte.to_string()).collect::<Vec<String>>();
+		match format {
+			OutputFormat::Text => self.output_utf8_lossy(),
+			OutputFormat::Ascii => self.output.iter().map(|&byte| match byte {
+				b' '..=b'~' | b'\n' | b'\r' | b'\t' => byte as char,
+				_ => '.'
+			}).collect(),
+			OutputFormat::Spaced => numbers().join(" "),
+			OutputFormat::Lines => numbers().iter().map(|number| format!("{}\n", number)).collect()
+		}
+	}
+}
+
+fn merge_statistics<T, F: FnOnce(T, T) -> T>(first: Option<T>, second: Option<T>, merge: F) -> Option<T> {
+	match (first, second) {
+		(Some(first), Some(second)) => Some(merge(first, second)),
+		(first, second) => first.or(second)
+	}
 }
 
-type RuntimeResult = Result<&'static str, &'static str>;
+// a compact summary for command-line tools, e.g.
+// 906 executions in 1.24ms, finished
+// output: "Hello World!\n"
+impl<C: Cell> fmt::Display for RuntimeProduct<C> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} executions in {}, ", self.executions, format_duration(self.time))?;
+		match (self.error, self.termination) {
+			(Some(error), _) => writeln!(f, "failed: {}", error)?,
+			(None, Termination::Aborted) => writeln!(f, "aborted")?,
+			(None, _) => writeln!(f, "finished")?
+		}
+		write!(f, "output: {:?}", self.output_utf8_lossy())
+	}
+}
+
+// nanoseconds in the largest unit that keeps at least one whole digit
+fn format_duration(ns: u64) -> String {
+	match ns {
+		0..=999 => format!("{}ns", ns),
+		1_000..=999_999 => format!("{:.2}\u{b5}s", ns as f64 / 1e3),
+		1_000_000..=999_999_999 => format!("{:.2}ms", ns as f64 / 1e6),
+		_ => format!("{:.2}s", ns as f64 / 1e9)
+	}
+}
+
+// what ',' stores once the input has been exhausted
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum EofBehavior {
+	Zero,
+	MaxValue, // 255 for byte cells, the default
+	Unchanged,
+	Value(u8) // a sentinel of the program's choosing, to match a particular reference interpreter
+}
+
+// how the tape behaves when the pointer runs off either end
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TapeMode {
+	Growable, // grows to the right on demand and errors below zero, the default
+	FixedWrapping(usize), // a circular tape of this many cells
+	FixedClamped(usize), // this many cells, with the pointer saturating at either end
+	Bidirectional // grows on demand in both directions, so the pointer may go negative
+}
 
-pub struct Runtime {
-	instructions: String,
+// how many cells a growable tape adds each time the pointer runs off its end
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum GrowthStrategy {
+	Doubling, // as many cells as the tape already has
+	Factor(f64), // to this multiple of the current length, plus one cell; the default is Factor(1.5)
+	Fixed(usize) // this many cells at a time
+}
+
+impl GrowthStrategy {
+
+	// always at least one cell, since growing by nothing means the memory limit was hit
+	fn additional(&self, length: usize) -> usize {
+		match *self {
+			GrowthStrategy::Doubling => length.max(1),
+			GrowthStrategy::Factor(factor) => ((length as f64 * (factor - 1.0)) as usize).saturating_add(1), // the cast saturates, and a factor below 1 grows by one
+			GrowthStrategy::Fixed(cells) => cells.max(1)
+		}
+	}
+
+}
+
+impl Default for GrowthStrategy {
+	fn default() -> GrowthStrategy {
+		GrowthStrategy::Factor(1.5)
+	}
+}
+
+// how memory_dump() spells each cell
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Radix {
+	Hex, // zero-padded to the cell width
+	Decimal,
+	Char // printable ASCII as itself, anything else as '.'
+}
+
+// how much of the output the buffer keeps
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum OutputMode {
+	Buffered, // all of it, the default
+	Ring(usize) // only the last this many bytes, so a chatty program's output takes bounded memory
+}
+
+// how formatted_output() renders the output, for programs whose '.' emits text or numbers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+	Text, // UTF-8, with invalid sequences replaced by U+FFFD
+	Ascii, // printable ASCII and whitespace as itself, anything else as '.'
+	Spaced, // each byte as a decimal number, separated by spaces
+	Lines // each byte as a decimal number on its own line
+}
+
+// whether an error ends the run, or is only reported in its snapshot while execution continues
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecoveryMode {
+	Fatal, // the default for every error
+	Recover
+}
+
+// how many instructions run between checks of the time limit
+const TIME_LIMIT_CHECK_INTERVAL: usize = 1024;
+
+type RuntimeResult = Result<&'static str, RuntimeError>;
+
+pub struct Runtime<C: Cell = u8> {
+	instructions: Vec<u8>, // every instruction is ASCII, so index by byte rather than by char
 	instruction_pointer: usize,
 
-	input: Vec<u8>,
-	input_pointer: usize,
+	input: InputSource, // the active input stream
+	input_pointer: usize, // bytes consumed so far from the active stream, whatever the source
+	parked_inputs: VecDeque<(InputSource, usize)>, // the other streams with their pointers, next to become active first
+	active_input: usize, // index of the active stream, in the order the streams were added
+	validate_ascii_input: bool, // fail on input that isn't printable ASCII text
 
-	memory: Vec<u8>,
+	memory: Vec<C>, // every cell up to len() is initialized; the pointers always index below len(), never capacity()
+	initial_memory: usize, // cells allocated up front, and again on reset
+	fill_value: u8, // what fresh and grown cells hold
+	initial_tape: Vec<C>, // cells preloaded from the origin on, and again on reset
 	memory_pointer: usize,
+	memory_pointer_max: usize, // highest cell touched so far, which bounds each snapshot's copy of memory
+	origin: usize, // index into memory of logical cell 0; cells grown to the left push it up
+
+	output: Vec<u8>, // in a ring, up to twice its size with the oldest bytes first, trimmed back as it fills
+	output_mode: OutputMode,
+	#[cfg(feature = "std")]
+	output_sink: Option<Box<dyn Write + Send>>, // when set, '.' streams here instead of into output
+	#[cfg(feature = "std")]
+	output_tee: Option<Box<dyn Write + Send>>, // when set, '.' also mirrors every byte here
+	output_written: usize, // bytes emitted so far, whether buffered or streamed
+	consumed_input: Vec<u8>, // every byte ',' has read, for replaying the run
+
+	eof_behavior: EofBehavior,
+	tape_mode: TapeMode,
+	grow_left: bool, // prepend cells rather than underflow, on a growable tape
+	growth_strategy: GrowthStrategy,
+	signed_cells: bool, // wrap at the two's complement boundary (127 to -128 for bytes) instead of at zero
+
+	record_snapshots: bool, // when false, run() only executes and counts
 
-	output: Vec<u8>,
+	profile: bool, // count executed instructions by kind and the values written to cells
+	instruction_profile: InstructionProfile,
+	value_histogram: Vec<u64>, // always 256 counts, one per byte value
+	loop_iterations: BTreeMap<usize, u64>, // jumps back by the offset of the loop's '['
+	track_wraps: bool, // count every time +- carries a cell past its maximum or below zero
+	wraps: usize,
+	track_touched: bool, // mark every cell an instruction writes
+	touched: Vec<u64>, // bitset by index into memory, only as long as the highest cell marked needs
+	time_instructions: bool, // read the clock around every instruction, which is slow
+	instruction_times: Vec<u64>, // indexed by source offset, grown to the source length on first use
+	scanned_instructions: usize, // source bytes jumped over by brackets since the last reset
+
+	instruction_set: InstructionSet, // the tokens each operation is spelled with, standard Brainfuck by default
+	debug_dump: bool, // treat '#' as a request for a snapshot rather than a comment
+	zero_cell: bool, // treat '@' as setting the current cell to zero rather than a comment
+	warnings_as_errors: bool, // refuse to run a program analyze() has anything to say about
+	strict_mode: bool, // refuse to run a program with comments other than whitespace
+	error_dump: bool, // a fatal error's snapshot holds the whole tape, and is kept even when not recording
+	optimize: bool, // fold runs of identical instructions; off keeps one op per source instruction for debugging
+
+	program: Option<Vec<Instruction>>, // compiled before the first instruction executes
+	op_pointer: usize, // index into program of the next op, kept in step with instruction_pointer
+
+	breakpoints: Vec<usize>, // sorted source offsets that run_until_break() stops in front of
+	paused: bool, // stopped at a breakpoint, which must not fire again until execution moves on
+	watchpoints: Vec<(usize, Option<C>)>, // cell indices from the origin, each with an optional value to wait for
+	error: Option<RuntimeError>, // set once a fatal error has been hit
+	recoverable: Vec<RuntimeError>, // errors that are reported without ending the run
+
+	clock: Box<dyn Clock>, // timing for the product and the time limit
+	cancel_token: Option<Arc<AtomicBool>>, // set from anywhere to stop the run in progress
+	cancel_check_interval: usize, // instructions between looks at the cancel token
 
 	execution_limit: usize,
-	memory_limit: usize
+	memory_limit: usize,
+	output_limit: usize,
+	time_limit_ns: u64,
+	total_memory_limit: usize, // bytes held by the tape, the output and the run's snapshots together
+	loop_iteration_limit: usize, // passes through any one loop's body each time it is entered
+	loop_passes: Vec<usize>, // by source offset of each '[', passes since the loop was last entered
+	max_program_len: usize, // source bytes, comments included
+	max_instructions: usize // tokens the source compiles from, comments excluded
 }
 
+// cell widths other than the default byte, for callers who would rather not spell out the type parameter
+pub type Runtime16 = Runtime<u16>;
+pub type Runtime32 = Runtime<u32>;
+
 impl Runtime {
 
 	pub fn new(instructions: String, input: Vec<u8>) -> Runtime {
@@ -71,201 +490,1461 @@ impl Runtime {
 	}
 
 	pub fn with_limits(instructions: String, input: Vec<u8>, execution_limit: usize, memory_limit: usize) -> Runtime {
+		Runtime::with_cell_size(instructions, input, execution_limit, memory_limit)
+	}
+
+	// stream output to sink as it is produced, rather than buffering it into the product
+	#[cfg(feature = "std")]
+	pub fn with_output<W: Write + Send + 'static>(instructions: String, input: Vec<u8>, sink: W) -> Runtime {
+		let mut runtime = Runtime::new(instructions, input);
+		runtime.set_output(sink);
+		runtime
+	}
+
+	// pull input lazily from source, one byte per ',', rather than from a preloaded buffer
+	#[cfg(feature = "std")]
+	pub fn with_input<R: Read + Send + 'static>(instructions: String, source: R) -> Runtime {
+		let mut runtime = Runtime::new(instructions, Vec::new());
+		runtime.set_input(source);
+		runtime
+	}
+
+	// ask callback for each byte as ',' needs it; returning None triggers the end-of-input behavior
+	pub fn with_input_callback<F: FnMut() -> Option<u8> + Send + 'static>(instructions: String, callback: F) -> Runtime {
+		let mut runtime = Runtime::new(instructions, Vec::new());
+		runtime.set_input_callback(callback);
+		runtime
+	}
+
+	// split a combined "program!input" source at the first '!', as many web interpreters accept;
+	// without a '!' the whole source is the program and the input is empty
+	pub fn from_combined(source: String) -> Runtime {
+		let mut instructions = source.into_bytes();
+		let input = match instructions.iter().position(|&byte| byte == b'!') {
+			Some(separator) => {
+				let input = instructions.split_off(separator + 1);
+				instructions.pop(); // the '!' itself
+				input
+			},
+			None => Vec::new()
+		};
+
+		let mut runtime = Runtime::new(String::new(), input);
+		runtime.instructions = instructions;
+		runtime
+	}
+
+}
+
+impl<C: Cell> Runtime<C> {
+
+	// like with_limits, but for any cell width; the width is picked by the type, e.g. Runtime16::with_cell_size(...)
+	pub fn with_cell_size(instructions: String, input: Vec<u8>, execution_limit: usize, memory_limit: usize) -> Runtime<C> {
 		Runtime {
-			instructions: instructions,
+			instructions: instructions.into_bytes(),
 			instruction_pointer: 0,
 
-			input: input,
+			input: InputSource::Buffer(input),
 			input_pointer: 0,
+			parked_inputs: VecDeque::new(),
+			active_input: 0,
+			validate_ascii_input: false,
 
-			memory: vec![0; 1],
+			memory: vec![C::ZERO; 1],
+			initial_memory: 1,
+			fill_value: 0,
+			initial_tape: Vec::new(),
 			memory_pointer: 0,
+			memory_pointer_max: 0,
+			origin: 0,
 
 			output: Vec::new(),
+			output_mode: OutputMode::Buffered,
+			#[cfg(feature = "std")]
+			output_sink: None,
+			#[cfg(feature = "std")]
+			output_tee: None,
+			output_written: 0,
+			consumed_input: Vec::new(),
+
+			eof_behavior: EofBehavior::MaxValue,
+			tape_mode: TapeMode::Growable,
+			grow_left: false,
+			growth_strategy: GrowthStrategy::default(),
+			signed_cells: false,
+
+			record_snapshots: true,
+
+			profile: false,
+			instruction_profile: InstructionProfile::default(),
+			value_histogram: vec![0; 256],
+			loop_iterations: BTreeMap::new(),
+			track_wraps: false,
+			wraps: 0,
+			track_touched: false,
+			touched: Vec::new(),
+			time_instructions: false,
+			instruction_times: Vec::new(),
+			scanned_instructions: 0,
+
+			instruction_set: InstructionSet::standard(),
+			debug_dump: false,
+			zero_cell: false,
+			warnings_as_errors: false,
+			strict_mode: false,
+			error_dump: false,
+			optimize: false,
+
+			program: None,
+			op_pointer: 0,
+
+			breakpoints: Vec::new(),
+			paused: false,
+			watchpoints: Vec::new(),
+			error: None,
+			recoverable: Vec::new(),
+
+			clock: clock::default_clock(),
+			cancel_token: None,
+			cancel_check_interval: 1024,
+
+			execution_limit,
+			memory_limit,
+			output_limit: 0,
+			time_limit_ns: 0,
+			total_memory_limit: 0,
+			loop_iteration_limit: 0,
+			loop_passes: Vec::new(),
+			max_program_len: 0,
+			max_instructions: 0
+		}
+	}
+
+	// rebuild a runtime positioned right after snapshot was taken, for time-travel debugging; instructions and
+	// input must be the ones the snapshot came from. The restored state is the snapshot's memory (cells past
+	// it were never touched, so are zero), memory pointer and origin, output, and its resume and input pointers,
+	// the last of which decides what the next ',' reads. Limits and options start from their defaults.
+	pub fn from_snapshot(instructions: String, input: Vec<u8>, snapshot: &RuntimeSnapshot<C>) -> Runtime<C> {
+		let mut runtime = Runtime::with_cell_size(instructions, input, 0, 0);
+
+		runtime.instruction_pointer = snapshot.resume_pointer;
+		runtime.input_pointer = snapshot.input_pointer;
+
+		runtime.memory = snapshot.memory.clone();
+		if runtime.memory.is_empty() {
+			runtime.memory.push(C::ZERO);
+		}
+		runtime.memory_pointer = snapshot.memory_pointer;
+		runtime.memory_pointer_max = runtime.memory.len() - 1;
+		runtime.origin = snapshot.origin;
+
+		runtime.output = snapshot.output.clone();
+		runtime.output_written = runtime.output.len();
+		runtime
+	}
+
+	// a fresh runtime that feeds the same program the input product's run read, as one buffer, to check that running
+	// it again reproduces the product's output and executions, e.g. after changing the engine. Limits and options start
+	// from their defaults, so set them as they were; a run over several ';' streams can't be replayed this way
+	pub fn replay(instructions: String, product: &RuntimeProduct<C>) -> Runtime<C> {
+		Runtime::with_cell_size(instructions, product.input.clone(), 0, 0)
+	}
 
-			execution_limit: execution_limit,
-			memory_limit: memory_limit
+	pub fn instruction_pointer(&self) -> usize {
+		self.instruction_pointer
+	}
+
+	pub fn input_pointer(&self) -> usize {
+		self.input_pointer
+	}
+
+	// unread bytes in the active input, e.g. for deciding whether to prompt for more; a reader or callback can't
+	// say how much it has left, so counts as none
+	pub fn input_remaining(&self) -> usize {
+		match self.input {
+			InputSource::Buffer(ref buffer) => buffer.len().saturating_sub(self.input_pointer),
+			InputSource::Random { len, .. } => len.saturating_sub(self.input_pointer),
+			_ => 0
+		}
+	}
+
+	// bytes emitted so far, counting those streamed to an output sink
+	pub fn output_len(&self) -> usize {
+		self.output_written
+	}
+
+	pub fn memory_pointer(&self) -> usize {
+		self.memory_pointer
+	}
+
+	pub fn current_cell(&self) -> C {
+		self.memory[self.memory_pointer]
+	}
+
+	// the cell at a logical index, the fill value for cells the tape hasn't grown to yet
+	fn logical_cell(&self, index: isize) -> C {
+		let physical = self.origin as isize + index;
+		if (physical < 0) || (physical >= self.memory.len() as isize) {
+			return self.fill();
 		}
+
+		self.memory[physical as usize]
+	}
+
+	// logical indices of the cells the next op may write: the one under the pointer, and a fused loop's targets
+	fn written_cells(&self) -> Vec<isize> {
+		let pointer = self.memory_pointer as isize - self.origin as isize;
+		match self.current_instruction().map(|instruction| instruction.op) {
+			Some(Op::Add(_)) | Some(Op::Sub(_)) | Some(Op::Clear(_)) | Some(Op::Zero) | Some(Op::Input) => vec![pointer],
+			Some(Op::Multiply(targets)) => {
+				let mut cells = vec![pointer];
+				if let Some(ref program) = self.program {
+					for target in &program[(self.op_pointer + 1)..(self.op_pointer + 1 + targets)] {
+						if let Op::Target(offset, _) = target.op {
+							cells.push(pointer + offset);
+						}
+					}
+				}
+				cells
+			},
+			_ => Vec::new()
+		}
+	}
+
+	// the tape up to the highest cell touched so far, the same span a snapshot copies
+	pub fn memory_slice(&self) -> &[C] {
+		&self.memory[..(self.memory_pointer_max + 1)]
+	}
+
+	// the cells within radius of the pointer, cut short where the tape ends on either side, along with the
+	// pointer's index into them; for rendering the neighborhood of the pointer rather than the whole tape
+	pub fn window(&self, radius: usize) -> (&[C], usize) {
+		let start = self.memory_pointer.saturating_sub(radius);
+		let end = self.memory_pointer.saturating_add(radius).saturating_add(1).min(self.memory.len());
+		(&self.memory[start..end], self.memory_pointer - start)
+	}
+
+	// memory_slice() as one line of space-separated cells, with the cell under the pointer in brackets
+	pub fn memory_dump(&self, radix: Radix) -> String {
+		let width = 2 * mem::size_of::<C>();
+		let cells: Vec<String> = self.memory_slice().iter().enumerate().map(|(index, &cell)| {
+			let formatted = match radix {
+				Radix::Hex => format!("{:0width$X}", cell, width = width),
+				Radix::Decimal => cell.to_string(),
+				Radix::Char => match cell.to_byte() {
+					byte @ 0x20..=0x7E if cell == C::from_byte(byte) => (byte as char).to_string(),
+					_ => ".".to_string()
+				}
+			};
+
+			if index == self.memory_pointer { format!("[{}]", formatted) } else { formatted }
+		}).collect();
+
+		cells.join(" ")
 	}
 
 	fn expand_memory(&mut self) -> usize {
-		let mut additional = (self.memory.capacity() / 2) + 1; // try to reserve 50% of the current capacity more plus one
-		if (self.memory_limit > 0) && ((self.memory.capacity() + additional) > self.memory_limit) {
-			additional = self.memory_limit - self.memory.capacity();
+		let mut additional = self.growth_strategy.additional(self.memory.len());
+		if (self.memory_limit > 0) && ((self.memory.len() + additional) > self.memory_limit) {
+			additional = self.memory_limit.saturating_sub(self.memory.len()); // never past the limit'th cell
 		}
 
+		let length = self.memory.len() + additional;
 		self.memory.reserve_exact(additional);
-		self.memory.extend(vec![0; additional]);
+		self.memory.resize(length, self.fill()); // len() grows with the reservation, so no cell is ever left past it
 		additional
 	}
 
-	fn next_input_byte(&mut self) -> u8 {
-		if self.input_pointer >= self.input.len() {
-			return 255; // TODO: -1?
+	// rewind to the state right after construction, keeping the instructions, input, limits, options and compiled program;
+	// a streaming input source is not rewound, so it continues where it left off
+	pub fn reset(&mut self) {
+		self.instruction_pointer = 0;
+		self.op_pointer = 0;
+		self.paused = false;
+
+		for _ in 0..self.parked_inputs.len() { // back to the first stream, with the rest in their original order
+			if self.active_input == 0 {
+				break;
+			}
+			self.rotate_inputs();
+		}
+		self.active_input = 0; // already so, unless restored state named a stream that doesn't exist
+		self.input_pointer = 0;
+		for parked in self.parked_inputs.iter_mut() {
+			parked.1 = 0;
+		}
+
+		self.rewind_memory();
+
+		self.output.clear();
+		self.output_written = 0;
+		self.consumed_input.clear();
+		self.error = None;
+
+		self.instruction_profile = InstructionProfile::default();
+		self.value_histogram = vec![0; 256];
+		self.loop_iterations.clear();
+		self.loop_passes.clear();
+		self.wraps = 0;
+		self.touched.clear();
+		self.instruction_times.clear();
+		self.scanned_instructions = 0;
+	}
+
+	#[cfg(feature = "std")]
+	pub fn set_output<W: Write + Send + 'static>(&mut self, sink: W) {
+		self.output_sink = Some(Box::new(sink));
+	}
+
+	// mirror every byte '.' emits to tee as it happens, on top of buffering or streaming it as usual, e.g. to watch
+	// a run live on stdout while its product still gets the full output; a failing tee only shows in the snapshot message
+	#[cfg(feature = "std")]
+	pub fn set_tee_output<W: Write + Send + 'static>(&mut self, tee: W) {
+		self.output_tee = Some(Box::new(tee));
+	}
+
+	fn flush_output(&mut self) -> RuntimeResult {
+		#[cfg(feature = "std")]
+		if let Some(ref mut sink) = self.output_sink {
+			sink.flush()?;
 		}
 
-		let result = self.input[self.input_pointer];
-		self.input_pointer += 1;
-		result
+		Ok("flushed output sink")
 	}
 
-	fn increment_pointer(&mut self) -> RuntimeResult {
-		// ensure capacity
-		if (self.memory_pointer + 1) >= self.memory.capacity() {
-			// TODO: memory limit check?
-			if self.expand_memory() == 0 {
-				return Err("failed to increment pointer (runtime memory limit exceeded)");
+	#[cfg(feature = "std")]
+	pub fn set_input<R: Read + Send + 'static>(&mut self, source: R) {
+		self.input = InputSource::Reader(Box::new(source));
+	}
+
+	// add another buffered input stream, which enables ';' to cycle through the streams in the order they were added;
+	// ',' reads from whichever is active, each stream keeping its own position
+	pub fn add_input_stream(&mut self, input: Vec<u8>) {
+		self.parked_inputs.push_back((InputSource::Buffer(input), 0));
+		self.program = None; // ';' is a comment until there is a stream to switch to
+	}
+
+	// index of the stream ',' reads from, 0 being the one the runtime was created with
+	pub fn active_input(&self) -> usize {
+		self.active_input
+	}
+
+	fn rotate_inputs(&mut self) {
+		if let Some((input, input_pointer)) = self.parked_inputs.pop_front() {
+			let active = mem::replace(&mut self.input, input);
+			self.parked_inputs.push_back((active, self.input_pointer));
+			self.input_pointer = input_pointer;
+			self.active_input = (self.active_input + 1) % (self.parked_inputs.len() + 1);
+		}
+	}
+
+	// the extension symbols currently recognized, for the compiler
+	fn extensions(&self) -> Vec<u8> {
+		let mut extensions = Vec::new();
+		if self.debug_dump {
+			extensions.push(b'#');
+		}
+		if !self.parked_inputs.is_empty() {
+			extensions.push(b';');
+		}
+		if self.zero_cell {
+			extensions.push(b'@');
+		}
+		extensions
+	}
+
+	pub fn set_input_callback<F: FnMut() -> Option<u8> + Send + 'static>(&mut self, callback: F) {
+		self.input = InputSource::Callback(Box::new(callback));
+	}
+
+	// read len pseudo-random bytes as input, the same ones every time for the same seed, e.g. to run a program
+	// against many reproducible inputs in a property test; saved state keeps them as a buffered input
+	pub fn set_random_input(&mut self, seed: u64, len: usize) {
+		self.input = InputSource::Random { seed, len };
+	}
+
+	// only accept input bytes that are printable ASCII, tab, line feed or carriage return, failing with
+	// NonAsciiInput at the first other one. A buffered input is checked before anything executes; streams,
+	// callbacks and the other buffered streams are checked byte by byte as ',' reads them
+	pub fn set_validate_ascii_input(&mut self, validate_ascii_input: bool) {
+		self.validate_ascii_input = validate_ascii_input;
+	}
+
+	fn check_input(&self) -> Result<(), RuntimeError> {
+		if let InputSource::Buffer(ref buffer) = self.input {
+			if self.validate_ascii_input {
+				let unread = buffer.get(self.input_pointer..).unwrap_or(&[]);
+				if let Some(offset) = unread.iter().position(|&byte| !is_text_byte(byte)) {
+					return Err(RuntimeError::NonAsciiInput(unread[offset], self.input_pointer + offset));
+				}
 			}
 		}
+		Ok(())
+	}
+
+	pub fn set_eof_behavior(&mut self, eof_behavior: EofBehavior) {
+		self.eof_behavior = eof_behavior;
+	}
+
+	// the tape a run starts with: a fixed mode's exact length, otherwise initial_memory or the initial tape,
+	// whichever is longer, within the memory limit; the initial tape is cut short if it doesn't fit
+	fn fresh_memory(&self) -> Vec<C> {
+		let wanted = self.initial_memory.max(self.initial_tape.len());
+		let length = match self.tape_mode {
+			TapeMode::FixedWrapping(length) | TapeMode::FixedClamped(length) => length,
+			TapeMode::Growable | TapeMode::Bidirectional if self.memory_limit > 0 => wanted.min(self.memory_limit),
+			TapeMode::Growable | TapeMode::Bidirectional => wanted
+		};
+
+		let mut memory = vec![self.fill(); length.max(1)];
+		let preloaded = self.initial_tape.len().min(memory.len());
+		memory[..preloaded].copy_from_slice(&self.initial_tape[..preloaded]);
+		memory
+	}
 
-		self.memory_pointer += 1; // increment the pointer
-		Ok("incremented pointer by 1")
+	// swap in a fresh tape with the pointer back at the origin; preloaded cells count as touched
+	fn rewind_memory(&mut self) {
+		self.memory = self.fresh_memory();
+		self.memory_pointer = 0;
+		self.memory_pointer_max = self.initial_tape.len().min(self.memory.len()).saturating_sub(1);
+		self.origin = 0;
 	}
 
-	fn decrement_pointer(&mut self) -> RuntimeResult {
-		if self.memory_pointer == 0 { // can't decrement to below zero
-			return Err("can't decrement pointer sub-0!");
+	// the value every cell starts at, in place of zero, for programs that assume some other background value: the
+	// tape is refilled with it right away, and it fills every cell the tape grows by. Default 0
+	pub fn set_fill_value(&mut self, fill_value: u8) {
+		self.fill_value = fill_value;
+		self.rewind_memory();
+	}
+
+	fn fill(&self) -> C {
+		C::from_byte(self.fill_value)
+	}
+
+	// preload the tape with cells, starting at the origin; the tape still grows past them as usual.
+	// This rewinds the tape and the pointer right away, and every reset() starts from the same cells
+	pub fn set_initial_tape(&mut self, initial_tape: Vec<C>) {
+		self.initial_tape = initial_tape;
+		self.rewind_memory();
+	}
+
+	// pre-size the tape so pointer-heavy programs don't have to grow it step by step; a smaller tape
+	// is grown to match right away, and every reset() starts from this size
+	pub fn set_initial_memory(&mut self, initial_memory: usize) {
+		self.initial_memory = initial_memory;
+
+		let length = self.fresh_memory().len();
+		if self.memory.len() < length {
+			self.memory.reserve_exact(length - self.memory.len());
+			self.memory.resize(length, self.fill());
 		}
+	}
 
-		self.memory_pointer -= 1;
-		Ok("decremented pointer by 1")
+	// on a growable tape, let '<' past the first cell add cells before it instead of failing with PointerUnderflow;
+	// unlike a bidirectional tape the new cells take the low indices, so every existing cell's index goes up by as
+	// many and snapshots only ever see indices from 0. Each such move copies the whole tape
+	pub fn set_grow_left(&mut self, grow_left: bool) {
+		self.grow_left = grow_left;
 	}
 
-	fn increment_byte(&mut self) -> RuntimeResult {
-		if self.memory[self.memory_pointer] < 255 {
-			self.memory[self.memory_pointer] += 1;
-			return Ok("incremented byte by 1");
+	// fixed modes resize the tape to exactly their length, keeping whatever fits
+	pub fn set_tape_mode(&mut self, tape_mode: TapeMode) {
+		self.tape_mode = tape_mode;
+		self.program = None; // whether multiply loops can be fused depends on it
+
+		match tape_mode {
+			TapeMode::FixedWrapping(length) | TapeMode::FixedClamped(length) => {
+				let length = length.max(1);
+				self.memory.truncate(length);
+				self.memory.resize(length, self.fill());
+				self.memory.shrink_to_fit();
+
+				self.memory_pointer = self.memory_pointer.min(length - 1);
+				self.memory_pointer_max = self.memory_pointer_max.min(length - 1);
+			},
+			TapeMode::Growable | TapeMode::Bidirectional => ()
+		}
+	}
+
+	// grow the tape to the left, shifting every physical index up; returns the number of cells added
+	fn expand_memory_left(&mut self) -> usize {
+		let additional = self.prepend_memory(self.growth_strategy.additional(self.memory.len())); // same growth as to the right
+		self.origin += additional;
+		additional
+	}
+
+	// add up to additional cells before the first, as the memory limit allows, with the pointers and touched cells
+	// moving along with the cells they were on; returns the number of cells added
+	fn prepend_memory(&mut self, mut additional: usize) -> usize {
+		if (self.memory_limit > 0) && ((self.memory.len() + additional) > self.memory_limit) {
+			additional = self.memory_limit.saturating_sub(self.memory.len());
+		}
+
+		let mut memory = Vec::with_capacity(self.memory.len() + additional);
+		memory.extend(vec![self.fill(); additional]);
+		memory.extend_from_slice(&self.memory);
+		self.memory = memory;
+
+		self.memory_pointer += additional;
+		self.memory_pointer_max += additional;
+		if !self.touched.is_empty() { // every marked cell moved right along with the rest of the tape
+			let touched = mem::take(&mut self.touched);
+			for index in 0..(touched.len() * 64) {
+				if touched[index / 64] & (1 << (index % 64)) != 0 {
+					self.mark_touched(index + additional);
+				}
+			}
+		}
+		additional
+	}
+
+	fn mark_touched(&mut self, index: usize) {
+		if self.touched.len() <= index / 64 {
+			self.touched.resize(index / 64 + 1, 0);
+		}
+		self.touched[index / 64] |= 1 << (index % 64);
+	}
+
+	fn next_input_byte(&mut self) -> Result<Option<u8>, RuntimeError> {
+		if self.input.is_stream() {
+			self.flush_output()?; // make sure any prompt is visible before blocking on input
+		}
+
+		let result = self.input.next_byte(self.input_pointer)?;
+		if let Some(byte) = result {
+			if self.validate_ascii_input && !is_text_byte(byte) {
+				return Err(RuntimeError::NonAsciiInput(byte, self.input_pointer));
+			}
+
+			self.input_pointer += 1;
+		}
+		Ok(result)
+	}
+
+	fn increment_pointer(&mut self, count: usize) -> RuntimeResult {
+		let tape_length = self.memory.len();
+		if (self.memory_pointer + count) >= tape_length {
+			match self.tape_mode {
+				TapeMode::FixedWrapping(_) => {
+					self.memory_pointer = (self.memory_pointer + count) % tape_length;
+					self.memory_pointer_max = tape_length - 1; // passed over every cell on the way around
+					return Ok("wrapped pointer around to start of tape");
+				},
+				TapeMode::FixedClamped(_) => {
+					self.memory_pointer = tape_length - 1;
+					self.memory_pointer_max = tape_length - 1;
+					return Ok("clamped pointer at end of tape");
+				},
+				TapeMode::Growable | TapeMode::Bidirectional => ()
+			}
+		}
+
+		// grow the tape until the destination cell exists; with a limit, cells 0 through memory_limit - 1 are reachable
+		while (self.memory_pointer + count) >= self.memory.len() {
+			if self.expand_memory() == 0 {
+				self.memory_pointer = self.memory.len() - 1; // as far as stepping one cell at a time would have reached
+				self.memory_pointer_max = self.memory_pointer_max.max(self.memory_pointer);
+				return Err(RuntimeError::MemoryLimitExceeded);
+			}
+		}
+
+		self.memory_pointer += count; // increment the pointer
+		self.memory_pointer_max = self.memory_pointer_max.max(self.memory_pointer);
+
+		if count == 1 {
+			Ok("incremented pointer by 1")
 		} else {
-			self.memory[self.memory_pointer] = 0;
-			return Ok("wrapped overflow byte back to 0x00");
+			Ok("incremented pointer by folded run")
 		}
 	}
 
-	fn decrement_byte(&mut self) -> RuntimeResult {
-		if self.memory[self.memory_pointer] > 0 {
-			self.memory[self.memory_pointer] -= 1;
-			return Ok("decremented byte by 1");
+	fn decrement_pointer(&mut self, count: usize) -> RuntimeResult {
+		if count > self.memory_pointer {
+			let tape_length = self.memory.len();
+			match self.tape_mode {
+				TapeMode::FixedWrapping(_) => {
+					self.memory_pointer = (self.memory_pointer + tape_length - (count % tape_length)) % tape_length;
+					self.memory_pointer_max = tape_length - 1; // passed over every cell on the way around
+					return Ok("wrapped pointer around to end of tape");
+				},
+				TapeMode::FixedClamped(_) => {
+					self.memory_pointer = 0;
+					return Ok("clamped pointer at start of tape");
+				},
+				TapeMode::Growable if self.grow_left => {
+					let missing = count - self.memory_pointer;
+					if self.prepend_memory(missing) < missing {
+						self.memory_pointer = 0;
+						return Err(RuntimeError::MemoryLimitExceeded);
+					}
+				},
+				TapeMode::Growable => { // can't decrement to below zero
+					self.memory_pointer = 0;
+					return Err(RuntimeError::PointerUnderflow);
+				},
+				TapeMode::Bidirectional => {
+					while count > self.memory_pointer {
+						if self.expand_memory_left() == 0 {
+							self.memory_pointer = 0; // as far as stepping one cell at a time would have reached
+							return Err(RuntimeError::MemoryLimitExceeded);
+						}
+					}
+				}
+			}
+		}
+
+		self.memory_pointer -= count;
+
+		if count == 1 {
+			Ok("decremented pointer by 1")
 		} else {
-			self.memory[self.memory_pointer] = 255;
-			return Ok("wrapped overflow byte back to 0xFF");
+			Ok("decremented pointer by folded run")
+		}
+	}
+
+	// cell plus or minus count, wrapping at the cell type's maximum, or at the signed maximum for signed cells;
+	// wraparounds are counted when tracking them
+	fn offset_cell(&mut self, cell: C, count: usize, increment: bool) -> (C, usize) {
+		let cell = if self.signed_cells { cell.flip_sign() } else { cell };
+		let (cell, wraps) = if increment { cell.add_count(count) } else { cell.sub_count(count) };
+		if self.track_wraps {
+			self.wraps += wraps;
+		}
+
+		(if self.signed_cells { cell.flip_sign() } else { cell }, wraps)
+	}
+
+	fn increment_byte(&mut self, count: usize) -> RuntimeResult {
+		let (this_cell, wraps) = self.offset_cell(self.memory[self.memory_pointer], count, true);
+		self.memory[self.memory_pointer] = this_cell;
+
+		match (count, wraps) {
+			(1, 0) => Ok("incremented byte by 1"),
+			(1, _) if self.signed_cells => Ok(C::SIGNED_OVERFLOW_MESSAGE),
+			(1, _) => Ok(C::OVERFLOW_MESSAGE),
+			(_, 0) => Ok("incremented byte by folded run"),
+			(_, _) => Ok("incremented byte by folded run, wrapping overflow")
+		}
+	}
+
+	fn decrement_byte(&mut self, count: usize) -> RuntimeResult {
+		let (this_cell, wraps) = self.offset_cell(self.memory[self.memory_pointer], count, false);
+		self.memory[self.memory_pointer] = this_cell;
+
+		match (count, wraps) {
+			(1, 0) => Ok("decremented byte by 1"),
+			(1, _) if self.signed_cells => Ok(C::SIGNED_UNDERFLOW_MESSAGE),
+			(1, _) => Ok(C::UNDERFLOW_MESSAGE),
+			(_, 0) => Ok("decremented byte by folded run"),
+			(_, _) => Ok("decremented byte by folded run, wrapping underflow")
+		}
+	}
+
+	fn clear_byte(&mut self, increment: bool) -> RuntimeResult {
+		if self.track_wraps { // counted as the loop would have, stepping all the way round to zero
+			let cell = self.memory[self.memory_pointer];
+			let steps = if increment { C::ZERO.sub_count(cell.to_count()).0.to_count() } else { cell.to_count() };
+			self.offset_cell(cell, steps, increment);
+		}
+		self.memory[self.memory_pointer] = C::ZERO;
+		Ok("cleared byte to zero")
+	}
+
+	// a fused multiply loop: add each target's multiple of the counter, then clear the counter. A loop that would
+	// fail to reach one of its targets is run unfused instead, so it fails at the same move with the same tape
+	fn multiply(&mut self, targets: usize) -> RuntimeResult {
+		let first = self.op_pointer + 1;
+		let fallback = first + targets;
+		self.op_pointer = match self.program.as_ref().map(|program| program[fallback].op) {
+			Some(Op::Open(close_index)) => close_index, // leave execute() to step past the unfused loop
+			_ => fallback - 1
+		};
+
+		let counter = self.memory[self.memory_pointer];
+		if counter == C::ZERO {
+			return Ok("byte is zero, no multiply necessary");
+		}
+
+		let targets: Vec<(isize, isize)> = self.program.as_ref().map_or(Vec::new(), |program| {
+			program[first..(first + targets)].iter().filter_map(|target| match target.op {
+				Op::Target(offset, factor) => Some((offset, factor)),
+				_ => None
+			}).collect()
+		});
+		let lowest = targets.iter().map(|&(offset, _)| offset).min().unwrap_or(0);
+		let highest = targets.iter().map(|&(offset, _)| offset).max().unwrap_or(0);
+
+		if !self.multiply_fits(lowest, highest) {
+			self.op_pointer = fallback - 1; // execute() steps onto the unfused loop's '['
+			return Ok("fused multiply loop runs off the tape, running it unfused");
+		}
+
+		while (self.memory_pointer as isize + highest) >= self.memory.len() as isize {
+			if self.expand_memory() == 0 {
+				return Err(RuntimeError::MemoryLimitExceeded);
+			}
+		}
+
+		if self.grow_left && (self.tape_mode == TapeMode::Growable) && ((self.memory_pointer as isize + lowest) < 0) {
+			let missing = (-(self.memory_pointer as isize + lowest)) as usize;
+			if self.prepend_memory(missing) < missing {
+				return Err(RuntimeError::MemoryLimitExceeded);
+			}
+		}
+
+		while (self.memory_pointer as isize + lowest) < 0 {
+			if self.tape_mode != TapeMode::Bidirectional {
+				return Err(RuntimeError::PointerUnderflow);
+			}
+			if self.expand_memory_left() == 0 {
+				return Err(RuntimeError::MemoryLimitExceeded);
+			}
+		}
+
+		let count = counter.to_count();
+		for (offset, factor) in targets {
+			let index = (self.memory_pointer as isize + offset) as usize;
+			let amount = count.wrapping_mul(factor.unsigned_abs()); // cells wrap at a power of two, so wrapping here is harmless
+			self.memory[index] = self.offset_cell(self.memory[index], amount, factor > 0).0; // wraps just as often as the loop would
 		}
+
+		if self.track_wraps {
+			self.offset_cell(counter, count, false); // the counter's own wrap, from a signed cell stepping down from below zero
+		}
+		self.memory[self.memory_pointer] = C::ZERO;
+		self.memory_pointer_max = self.memory_pointer_max.max((self.memory_pointer as isize + highest) as usize);
+		Ok("applied fused multiply loop")
+	}
+
+	// whether every cell from lowest to highest around the pointer is on the tape or can be grown onto it
+	fn multiply_fits(&self, lowest: isize, highest: isize) -> bool {
+		let pointer = self.memory_pointer as isize;
+		let left = (-(pointer + lowest)).max(0) as usize;
+		let right = (pointer + highest + 1 - self.memory.len() as isize).max(0) as usize;
+
+		let can_grow_left = (self.tape_mode == TapeMode::Bidirectional) || self.grow_left;
+		((left == 0) || can_grow_left) && ((self.memory_limit == 0) || (self.memory.len() + left + right <= self.memory_limit))
 	}
 
 	fn output_byte(&mut self) -> RuntimeResult {
-		// TODO: output length check?
-		let this_byte = self.memory[self.memory_pointer];
-		self.output.push(this_byte);
+		if (self.output_limit > 0) && (self.output_written >= self.output_limit) {
+			return Err(RuntimeError::OutputLimitExceeded);
+		}
+		self.output_written += 1;
+
+		let this_byte = self.memory[self.memory_pointer].to_byte(); // cells wider than a byte are truncated
 
-		Ok("copied byte from memory to output")
+		#[cfg(feature = "std")]
+		let teed = match self.output_tee {
+			Some(ref mut tee) => tee.write_all(&[this_byte]).and_then(|_| tee.flush()).is_ok(), // flushed so the mirror stays live
+			None => true
+		};
+		#[cfg(not(feature = "std"))]
+		let teed = true;
+
+		#[cfg(feature = "std")]
+		if let Some(ref mut sink) = self.output_sink {
+			sink.write_all(&[this_byte])?;
+			return Ok(if teed { "wrote byte from memory to output sink" } else { "wrote byte from memory to output sink, but not to tee" });
+		}
+
+		self.output.push(this_byte);
+		if let OutputMode::Ring(size) = self.output_mode {
+			if self.output.len() > size.saturating_mul(2) { // trimmed in batches, so each byte is moved at most once
+				let excess = self.output.len() - size;
+				self.output.drain(..excess);
+			}
+		}
+		Ok(if teed { "copied byte from memory to output" } else { "copied byte from memory to output, but not to tee" })
 	}
 
 	fn input_byte(&mut self) -> RuntimeResult {
-		self.memory[self.memory_pointer] = self.next_input_byte();
-		Ok("copied byte from input to memory")
+		if let Some(byte) = self.next_input_byte()? {
+			self.consumed_input.push(byte);
+			self.memory[self.memory_pointer] = C::from_byte(byte);
+			return Ok("copied byte from input to memory");
+		}
+
+		match self.eof_behavior {
+			EofBehavior::Zero => {
+				self.memory[self.memory_pointer] = C::ZERO;
+				Ok("input exhausted, stored zero")
+			},
+			EofBehavior::MaxValue => {
+				self.memory[self.memory_pointer] = C::MAX;
+				Ok("input exhausted, stored maximum cell value")
+			},
+			EofBehavior::Unchanged => Ok("input exhausted, left byte unchanged"),
+			EofBehavior::Value(value) => {
+				self.memory[self.memory_pointer] = C::from_byte(value);
+				Ok("input exhausted, stored end-of-input value")
+			}
+		}
 	}
 
-	fn handle_open_bracket(&mut self) -> RuntimeResult {
-		if self.memory[self.memory_pointer] == 0 {
+	// source bytes between two ops, which a scanning interpreter would have walked over one at a time
+	fn scan_distance(&self, from: usize, to: usize) -> usize {
+		self.program.as_ref().map_or(0, |program| {
+			let (from, to) = (program[from].offset, program[to].offset);
+			from.max(to) - from.min(to)
+		})
+	}
 
-			let mut open_count: u16 = 0;
-			loop {
-				if (self.instruction_pointer + 1) >= self.instructions.len() {
-					return Err("hit end of instructions w/o finding matching close bracket!");
+	fn handle_open_bracket(&mut self, close_index: usize) -> RuntimeResult {
+		if self.memory[self.memory_pointer] == C::ZERO {
+			self.scanned_instructions += self.scan_distance(self.op_pointer, close_index);
+			self.op_pointer = close_index;
+			Ok("found matching close bracket")
+		} else {
+			if self.loop_iteration_limit > 0 {
+				let open = self.program.as_ref().map_or(0, |program| program[self.op_pointer].offset);
+				if self.loop_passes.len() <= open {
+					self.loop_passes.resize(open + 1, 0);
 				}
+				self.loop_passes[open] = 1; // entering counts as the first pass
+			}
+			Ok("byte is non-zero, no bracket seek necessary")
+		}
+	}
 
-				self.instruction_pointer += 1;
-				match self.instructions.chars().nth(self.instruction_pointer).unwrap() {
-					'[' => open_count += 1,
-					']' => {
-						if open_count > 0 { // if there are open brackets left closed
-							open_count -= 1;
-						} else { // else, there are no open brackets left to consume
-							return Ok("found matching close bracket");
-						}
-					},
-					_ => ()
+	fn handle_close_bracket(&mut self, open_index: usize) -> RuntimeResult {
+		if self.memory[self.memory_pointer] != C::ZERO {
+			let open = self.program.as_ref().map_or(0, |program| program[open_index].offset);
+			if self.loop_iteration_limit > 0 {
+				if self.loop_passes.len() <= open {
+					self.loop_passes.resize(open + 1, 0); // entered before the limit was set
+				}
+				if self.loop_passes[open] >= self.loop_iteration_limit {
+					return Err(RuntimeError::LoopLimitExceeded(open));
 				}
+				self.loop_passes[open] += 1;
 			}
+			if self.profile {
+				*self.loop_iterations.entry(open).or_insert(0) += 1;
+			}
+			self.scanned_instructions += self.scan_distance(self.op_pointer, open_index);
+			self.op_pointer = open_index;
+			Ok("found matching open bracket")
 		} else {
-			return Ok("byte is non-zero, no bracket seek necessary");
+			Ok("byte is zero, no bracket seek necessary")
+		}
+	}
+
+	// check the program is well-formed without running it, returning the offset of the first offending byte
+	pub fn validate(&self) -> Result<(), (usize, RuntimeError)> {
+		self.check_size()?;
+		compiler::compile(&self.instructions, &self.instruction_set, &self.extensions(), false, false, false, &[])?;
+		self.check_strict()?;
+		self.check_warnings()
+	}
+
+	// reject a source longer than max_program_len bytes before it is even compiled, for programs from untrusted
+	// sources; 0 is unlimited. The run fails with ProgramTooLarge at the first byte past the limit
+	pub fn set_max_program_len(&mut self, max_program_len: usize) {
+		self.max_program_len = max_program_len;
+		self.program = None; // checked again before the next instruction executes
+	}
+
+	// likewise for the number of instructions, which unlike the length ignores comments
+	pub fn set_max_instructions(&mut self, max_instructions: usize) {
+		self.max_instructions = max_instructions;
+		self.program = None;
+	}
+
+	// the offset of the first byte or instruction past the size limits
+	fn check_size(&self) -> Result<(), (usize, RuntimeError)> {
+		if (self.max_program_len > 0) && (self.instructions.len() > self.max_program_len) {
+			return Err((self.max_program_len, RuntimeError::ProgramTooLarge));
 		}
+
+		if self.max_instructions > 0 {
+			if let Some(token) = compiler::tokenize(&self.instructions, &self.instruction_set, &self.extensions()).get(self.max_instructions) {
+				return Err((token.offset, RuntimeError::ProgramTooLarge));
+			}
+		}
+
+		Ok(())
 	}
 
-	fn handle_close_bracket(&mut self) -> RuntimeResult {
-		if self.memory[self.memory_pointer] != 0 {
+	pub fn set_warnings_as_errors(&mut self, warnings_as_errors: bool) {
+		if warnings_as_errors != self.warnings_as_errors {
+			self.warnings_as_errors = warnings_as_errors;
+			self.program = None; // checked again before the next instruction executes
+		}
+	}
+
+	// reject any byte that is neither part of an instruction nor ASCII whitespace, failing with UnknownInstruction
+	// at the first one before anything executes; disabled extensions' symbols count as unknown
+	pub fn set_strict_mode(&mut self, strict_mode: bool) {
+		if strict_mode != self.strict_mode {
+			self.strict_mode = strict_mode;
+			self.program = None; // checked again before the next instruction executes
+		}
+	}
+
+	// on a fatal error, capture the entire allocated tape in its snapshot rather than only the cells up to the furthest
+	// one touched, and keep that snapshot, with all the buffered output, even when snapshots are off, for post-mortems.
+	// Bytes already written to an output sink are not in the snapshot's output
+	pub fn set_error_dump(&mut self, error_dump: bool) {
+		self.error_dump = error_dump;
+	}
+
+	// choose whether error is fatal; only errors the engine can step past are recoverable: pointer underflow
+	// and the memory limit leave the pointer clamped at the end of the tape it ran off, and the output limit drops the byte.
+	// Every other error stays fatal whatever its mode
+	pub fn set_recovery_mode(&mut self, error: RuntimeError, mode: RecoveryMode) {
+		self.recoverable.retain(|&recoverable| recoverable != error);
+		if mode == RecoveryMode::Recover {
+			self.recoverable.push(error);
+		}
+		self.program = None; // recovering from pointer errors makes moves inexact
+	}
+
+	// whether a move always lands exactly count cells away, which fused multiply loops rely on;
+	// not so on a fixed tape, or when pointer errors clamp instead of ending the run
+	fn exact_moves(&self) -> bool {
+		matches!(self.tape_mode, TapeMode::Growable | TapeMode::Bidirectional)
+			&& !self.recoverable.contains(&RuntimeError::PointerUnderflow)
+			&& !self.recoverable.contains(&RuntimeError::MemoryLimitExceeded)
+	}
+
+	fn is_recoverable(&self, error: RuntimeError) -> bool {
+		match error {
+			RuntimeError::PointerUnderflow | RuntimeError::MemoryLimitExceeded | RuntimeError::OutputLimitExceeded => self.recoverable.contains(&error),
+			_ => false
+		}
+	}
+
+	// how far the tape grows at a time, in either direction; the memory limit still caps the tape whatever the strategy
+	pub fn set_growth_strategy(&mut self, growth_strategy: GrowthStrategy) {
+		self.growth_strategy = growth_strategy;
+	}
+
+	// interpret cells as two's complement, which moves only where +- wrap; cells are stored and
+	// output as the same raw bits either way, so a signed -1 byte still emits 0xFF
+	pub fn set_signed_cells(&mut self, signed_cells: bool) {
+		self.signed_cells = signed_cells;
+	}
+
+	pub fn set_profile(&mut self, profile: bool) {
+		self.profile = profile;
+	}
+
+	// count cell wraparounds, which are legal but often a bug, and report them in the product
+	pub fn set_track_wraps(&mut self, track_wraps: bool) {
+		self.track_wraps = track_wraps;
+	}
+
+	// record which cells are ever written, for RuntimeProduct::touched_cells(); one bit per cell, so cheap to keep
+	pub fn set_track_touched(&mut self, track_touched: bool) {
+		self.track_touched = track_touched;
+	}
+
+	// measure the time spent at each source offset to find the hot spots, at the cost of two clock reads per instruction
+	pub fn set_time_instructions(&mut self, time_instructions: bool) {
+		self.time_instructions = time_instructions;
+	}
+
+	// the time spent at every source offset, including those that have never executed
+	fn instruction_times_padded(&self) -> Vec<u64> {
+		let mut times = self.instruction_times.clone();
+		times.resize(self.instructions.len(), 0);
+		times
+	}
+
+	// read the source in another Brainfuck-family dialect
+	pub fn set_instruction_set(&mut self, instruction_set: InstructionSet) {
+		if instruction_set != self.instruction_set {
+			self.instruction_set = instruction_set;
+			self.program = None; // recompiled before the next instruction executes
+		}
+	}
+
+	pub fn set_debug_dump(&mut self, debug_dump: bool) {
+		if debug_dump != self.debug_dump {
+			self.debug_dump = debug_dump;
+			self.program = None; // recompiled before the next instruction executes
+		}
+	}
+
+	// the '@' extension some dialects add, zeroing the current cell in one step where [-] takes a step per value
+	pub fn set_zero_cell(&mut self, zero_cell: bool) {
+		if zero_cell != self.zero_cell {
+			self.zero_cell = zero_cell;
+			self.program = None; // recompiled before the next instruction executes
+		}
+	}
+
+	pub fn set_optimize(&mut self, optimize: bool) {
+		if optimize != self.optimize {
+			self.optimize = optimize;
+			self.program = None; // recompiled before the next instruction executes
+		}
+	}
+
+	// compile the program, rejecting unbalanced programs before anything executes, and find where to resume
+	fn prepare(&mut self) -> Result<(), (usize, RuntimeError)> {
+		let mut boundaries = self.breakpoints.clone();
+		if let Err(index) = boundaries.binary_search(&self.instruction_pointer) {
+			boundaries.insert(index, self.instruction_pointer);
+		}
+
+		self.check_size()?;
+		let fuse_loops = self.loop_iteration_limit == 0; // a fused loop has no passes to count
+		let program = compiler::compile(&self.instructions, &self.instruction_set, &self.extensions(), self.optimize, fuse_loops, self.exact_moves(), &boundaries)?;
+		self.check_strict()?;
+		self.check_warnings()?;
+		self.op_pointer = program.iter().position(|instruction| instruction.offset >= self.instruction_pointer).unwrap_or(program.len());
+		self.program = Some(program);
+		Ok(())
+	}
 
-			let mut close_count: u16 = 0;
-			loop {
-				if self.instruction_pointer <= 0 {
-					return Err("hit beginning of instructions w/o finding matching open bracket!")
+	// compile before the first instruction executes, recording a failure as fatal
+	fn ensure_program(&mut self) -> Result<(), (usize, RuntimeError)> {
+		if self.program.is_none() {
+			if let Err((offset, error)) = self.prepare() {
+				self.error = Some(error);
+				return Err((offset, error));
+			}
+		}
+
+		Ok(())
+	}
+
+	fn current_instruction(&self) -> Option<Instruction> {
+		self.program.as_ref().and_then(|program| program.get(self.op_pointer).cloned())
+	}
+
+	// execute the next op, returning it and the source offset to report for it along with its result,
+	// or None once the program has finished or failed
+	fn execute(&mut self) -> Option<(Instruction, usize, RuntimeResult)> {
+		if self.error.is_some() {
+			return None;
+		}
+
+		if let Err((offset, error)) = self.ensure_program() {
+			return Some((Instruction { op: Op::Open(0), offset }, offset, Err(error))); // stands in for whatever the program was rejected at
+		}
+
+		let instruction = self.current_instruction()?;
+		self.paused = false;
+		let written = match instruction.op { // for the value histogram and touched cells
+			_ if !self.profile && !self.track_touched => Vec::new(),
+			Op::Clear(_) | Op::Multiply(_) if self.current_cell() == C::ZERO => Vec::new(), // skipped, so writes nothing
+			_ => self.written_cells()
+		};
+		let pointer = self.memory_pointer as isize - self.origin as isize; // written is numbered from here
+
+		let started = if self.time_instructions { self.clock.now_ns() } else { 0 };
+		let result = match instruction.op {
+			Op::Right(count) => self.increment_pointer(count),
+			Op::Left(count) => self.decrement_pointer(count),
+			Op::Add(count) => self.increment_byte(count),
+			Op::Sub(count) => self.decrement_byte(count),
+			Op::Output => self.output_byte(),
+			Op::Input => self.input_byte(),
+			Op::Open(close_index) => self.handle_open_bracket(close_index),
+			Op::Close(open_index) => self.handle_close_bracket(open_index),
+			Op::Clear(increment) => self.clear_byte(increment),
+			Op::Multiply(targets) => self.multiply(targets),
+			Op::Target(..) => Ok("skipped multiply target"), // unreachable, Multiply steps over its targets
+			Op::SwitchInput => {
+				self.rotate_inputs();
+				Ok("switched to next input stream")
+			},
+			Op::DebugDump => Ok("debug dump requested"),
+			Op::Zero => {
+				self.memory[self.memory_pointer] = C::ZERO;
+				Ok("set byte to zero by '@'")
+			}
+		};
+
+		if self.time_instructions {
+			let elapsed = self.clock.now_ns().saturating_sub(started);
+			if self.instruction_times.len() < self.instructions.len() {
+				self.instruction_times.resize(self.instructions.len(), 0);
+			}
+			self.instruction_times[instruction.offset] += elapsed;
+		}
+
+		let written: Vec<isize> = match instruction.op {
+			Op::Multiply(_) if !matches!(self.current_instruction().map(|instruction| instruction.op), Some(Op::Close(_))) => Vec::new(), // left to the unfused loop
+			Op::Multiply(_) => { // a fused loop stays put, so a pointer that moved means grow_left renumbered the tape under it
+				let renumbered = (self.memory_pointer as isize - self.origin as isize) - pointer;
+				written.into_iter().map(|index| index + renumbered).collect()
+			},
+			_ => written
+		};
+
+		if self.profile {
+			self.instruction_profile.record(instruction.op);
+			if result.is_ok() {
+				for &index in &written {
+					let value = self.logical_cell(index).to_byte();
+					self.value_histogram[value as usize] += 1;
 				}
+			}
+		}
 
-				self.instruction_pointer -= 1;
-				match self.instructions.chars().nth(self.instruction_pointer).unwrap() {
-					']' => close_count += 1,
-					'[' => {
-						if close_count > 0 { // if there are closed brackets left open
-							close_count -= 1;
-						} else {
-							return Ok("found matching open bracket");
-						}
-					},
-					_ => ()
+		if self.track_touched && result.is_ok() {
+			for index in written {
+				let physical = (index + self.origin as isize) as usize;
+				self.mark_touched(physical);
+			}
+		}
+
+		let position = match instruction.op {
+			Op::Multiply(_) => instruction.offset, // reported at its '[', wherever it leaves the op pointer
+			_ => self.current_instruction().map_or(instruction.offset, |instruction| instruction.offset) // a jump lands on the matching bracket
+		};
+		match result {
+			Err(error) if !self.is_recoverable(error) => self.error = Some(error),
+			_ => { // a recovered error is still reported in the snapshot, but execution carries on from the clamped state
+				self.op_pointer += 1;
+				self.instruction_pointer = self.current_instruction().map_or(self.instructions.len(), |instruction| instruction.offset);
+			}
+		}
+
+		Some((instruction, position, result))
+	}
+
+	// execute exactly one instruction, returning its snapshot, or None once the program has finished or failed
+	pub fn step(&mut self) -> Option<RuntimeSnapshot<C>> {
+		self.execute().map(|(_, position, result)| RuntimeSnapshot::new(self, position, result))
+	}
+
+	// step() until the program outputs its next byte and return it, or None once it finishes or fails first; a later
+	// call carries on from there. Nothing is snapshotted, a streaming sink is flushed after the byte, and the
+	// execution limit applies to each call, so a program that never outputs again gives up with None
+	pub fn next_output_byte(&mut self) -> Option<u8> {
+		let mut executions: usize = 0;
+		while (self.execution_limit == 0) || (executions < self.execution_limit) {
+			let (instruction, _, result) = self.execute()?;
+			executions += 1;
+			if (instruction.op == Op::Output) && result.is_ok() {
+				if let Err(error) = self.flush_output() {
+					self.error = Some(error);
+					return None;
 				}
+				return Some(self.current_cell().to_byte()); // '.' leaves the pointer on the cell it printed
 			}
-		} else {
-			return Ok("byte is zero, no bracket seek necessary");
 		}
+
+		None
+	}
+
+	// keep only the last so many bytes of buffered output in snapshots and products, rather than all of it; the
+	// output limit and output_len() still count every byte. Streamed output is not buffered, so is unaffected
+	pub fn set_output_mode(&mut self, output_mode: OutputMode) {
+		self.output_mode = output_mode;
+	}
+
+	// the buffered output as snapshots and products see it
+	fn output_tail(&self) -> &[u8] {
+		match self.output_mode {
+			OutputMode::Ring(size) => &self.output[self.output.len().saturating_sub(size)..],
+			OutputMode::Buffered => &self.output
+		}
+	}
+
+	// total bytes '.' may emit, buffered or streamed, 0 meaning unlimited
+	pub fn set_output_limit(&mut self, output_limit: usize) {
+		self.output_limit = output_limit;
+	}
+
+	// wall-clock budget for each run() in nanoseconds, 0 meaning unlimited
+	pub fn set_time_limit_ns(&mut self, time_limit_ns: u64) {
+		self.time_limit_ns = time_limit_ns;
+	}
+
+	// a byte budget for everything a run holds on to: the tape, the buffered output and the snapshots recorded so far,
+	// 0 meaning unlimited. It is checked before each instruction, and going over it is fatal with TotalMemoryLimitExceeded,
+	// unlike the execution and time limits; the counts are of heap allocations, so they are close to but not exactly the
+	// process's usage
+	pub fn set_total_memory_limit(&mut self, total_memory_limit: usize) {
+		self.total_memory_limit = total_memory_limit;
+	}
+
+	// end the run with LoopLimitExceeded, naming the loop's '[' offset, before any one loop runs its body more than
+	// loop_iteration_limit times in a row, to catch a runaway loop well inside the execution limit; 0 is unlimited.
+	// Each entry into a loop starts its count again. The optimizer leaves loops unfused while a limit is set
+	pub fn set_loop_iteration_limit(&mut self, loop_iteration_limit: usize) {
+		if (loop_iteration_limit == 0) != (self.loop_iteration_limit == 0) {
+			self.program = None; // whether loops can be fused depends on it
+		}
+		self.loop_iteration_limit = loop_iteration_limit;
+	}
+
+	// bytes allocated for the tape and the buffered output
+	fn footprint(&self) -> usize {
+		(self.memory.capacity() * mem::size_of::<C>()) + self.output.capacity()
+	}
+
+	// read the time from clock instead of the system clock, e.g. a MockClock for tests that assert on timing
+	pub fn set_clock<K: Clock + 'static>(&mut self, clock: K) {
+		self.clock = Box::new(clock);
+	}
+
+	// stop runs early, with an Aborted termination, once token is set, e.g. from another thread serving a cancel request;
+	// the token is not cleared, so the host has to reset it before running again
+	pub fn set_cancel_token(&mut self, token: Arc<AtomicBool>) {
+		self.cancel_token = Some(token);
+	}
+
+	// how many instructions run between checks of the cancel token, trading responsiveness for overhead; 1024 by default
+	pub fn set_cancel_check_interval(&mut self, cancel_check_interval: usize) {
+		self.cancel_check_interval = cancel_check_interval.max(1);
+	}
+
+	fn cancelled(&self, executions: usize) -> bool {
+		match self.cancel_token {
+			Some(ref token) => executions.is_multiple_of(self.cancel_check_interval) && token.load(Ordering::Relaxed),
+			None => false
+		}
+	}
+
+	fn elapsed_ns(&mut self, start: u64) -> u64 {
+		self.clock.now_ns().saturating_sub(start) // a clock that goes backwards reads as no time at all
+	}
+
+	pub fn set_record_snapshots(&mut self, record_snapshots: bool) {
+		self.record_snapshots = record_snapshots;
+	}
+
+	// end a run early because the engine refused to go further; the error is not stored on the runtime, so a later run() can resume
+	fn terminate(&mut self, error: RuntimeError, executions: usize, start: u64, mut snapshots: Vec<RuntimeSnapshot<C>>) -> RuntimeProduct<C> {
+		if self.record_snapshots {
+			snapshots.push(RuntimeSnapshot::new(self, self.instruction_pointer, Err(error))); // refusal message, not counted as an execution
+		}
+
+		let error = self.flush_output().err().unwrap_or(error);
+		let time = self.elapsed_ns(start);
+		RuntimeProduct::new(self, executions, time, snapshots, Some(error))
+	}
+
+	// whether a run records the snapshot of an instruction: every one when recording, otherwise only debug dumps
+	// and, with error_dump, the fatal error
+	fn keeps_snapshot(&self, op: Op, result: RuntimeResult) -> bool {
+		self.record_snapshots || (op == Op::DebugDump) || (self.error_dump && result.is_err() && self.error.is_some())
 	}
 
-	pub fn run(&mut self) -> RuntimeProduct {
-		let start = time::precise_time_ns(); // start the stopwatch
+	pub fn run(&mut self) -> RuntimeProduct<C> {
+		self.run_observed(|runtime, op, position, result, snapshots| {
+			if runtime.keeps_snapshot(op, result) { // dumps are kept even when not recording
+				snapshots.push(RuntimeSnapshot::new(runtime, position, result));
+			}
+			true
+		})
+	}
 
-		let mut snapshots: Vec<RuntimeSnapshot> = Vec::new();
-		let mut memory_pointer_max: usize = 0;
+	// run(), handing callback the snapshot of every executed instruction as it happens; returning false stops
+	// execution after that instruction without an error, and a later run() resumes from there
+	pub fn run_with_callback<F: FnMut(&RuntimeSnapshot<C>) -> bool>(&mut self, mut callback: F) -> RuntimeProduct<C> {
+		self.run_observed(|runtime, op, position, result, snapshots| {
+			let snapshot = RuntimeSnapshot::new(runtime, position, result);
+			let keep_going = callback(&snapshot);
+			if runtime.keeps_snapshot(op, result) {
+				snapshots.push(snapshot);
+			}
+			keep_going
+		})
+	}
 
-		while self.instruction_pointer < self.instructions.len() {
+	// run() until n more bytes have been output, buffered or streamed, pausing after the instruction that emits the
+	// last of them; a later call resumes from there, so a prompt-driven program can be fed one chunk at a time.
+	// A streaming sink is flushed at every pause, and the product's termination is Aborted while output remains;
+	// n of 0 executes nothing
+	pub fn run_until_output(&mut self, n: usize) -> RuntimeProduct<C> {
+		if (n == 0) && self.ensure_program().is_ok() { // nothing to wait for, so nothing runs; a rejected program is reported below
+			let mut product = RuntimeProduct::new(self, 0, 0, Vec::new(), self.error);
+			if self.error.is_none() && self.current_instruction().is_some() {
+				product.termination = Termination::Aborted;
+			}
+			return product;
+		}
 
-			// if the maximum number of instructions have already been stored
-			if (self.execution_limit > 0) && (snapshots.len() >= self.execution_limit) {
-				snapshots.push(RuntimeSnapshot::new(&self, memory_pointer_max, true, "execution terminated by engine (instruction limit exceeded)"));
+		let target = self.output_written + n;
+		self.run_observed(|runtime, op, position, result, snapshots| {
+			if runtime.keeps_snapshot(op, result) {
+				snapshots.push(RuntimeSnapshot::new(runtime, position, result));
+			}
+			runtime.output_written < target
+		})
+	}
 
-				let executions = snapshots.len() - 1;
-				return RuntimeProduct::new(executions, (time::precise_time_ns() - start), self.output.clone(), snapshots); // return early, subtract one from execution count to account for refusal message
+	// run(), checking each byte output against expected and stopping, Aborted, at the first one that differs or runs
+	// past its end; true only when the program completes having output exactly expected. Bytes are counted from
+	// where this run starts, so a resumed run is checked against what is still to come
+	pub fn run_against_expected(&mut self, expected: &[u8]) -> (bool, RuntimeProduct<C>) {
+		let start = self.output_written;
+		let mut matching = true;
+		let product = self.run_observed(|runtime, op, position, result, snapshots| {
+			if runtime.keeps_snapshot(op, result) {
+				snapshots.push(RuntimeSnapshot::new(runtime, position, result));
+			}
+			if (op == Op::Output) && result.is_ok() {
+				let index = runtime.output_written - start - 1;
+				matching = expected.get(index) == Some(&runtime.current_cell().to_byte());
 			}
+			matching
+		});
+
+		let matched = matching && (product.termination == Termination::Completed) && (self.output_written - start == expected.len());
+		(matched, product)
+	}
+
+	// run() for benchmarks and production use: no snapshots at all, not even debug dumps, just the output,
+	// the number of instructions executed and the elapsed nanoseconds. The loop takes nothing per step beyond the
+	// limit checks, which still apply
+	pub fn run_headless(&mut self) -> (Vec<u8>, usize, u64) {
+		let start = self.clock.now_ns();
+		let mut executions: usize = 0;
+
+		if self.ensure_program().is_ok() {
+			if let Err(error) = self.check_input() {
+				self.error = Some(error);
+			}
+		}
+
+		while self.error.is_none() && self.current_instruction().is_some() {
+			if (self.execution_limit > 0) && (executions >= self.execution_limit) {
+				break;
+			}
+			if (self.time_limit_ns > 0) && executions.is_multiple_of(TIME_LIMIT_CHECK_INTERVAL) && (self.elapsed_ns(start) > self.time_limit_ns) {
+				break;
+			}
+			if (self.total_memory_limit > 0) && (self.footprint() > self.total_memory_limit) {
+				self.error = Some(RuntimeError::TotalMemoryLimitExceeded);
+				break;
+			}
+			if self.cancelled(executions) || self.execute().is_none() {
+				break;
+			}
+			executions += 1;
+		}
+
+		if let Err(error) = self.flush_output() {
+			self.error = self.error.or(Some(error));
+		}
+		(self.output_tail().to_vec(), executions, self.elapsed_ns(start))
+	}
 
-			let mut result: Option<RuntimeResult> = None;
-			match self.instructions.chars().nth(self.instruction_pointer).unwrap() {
-				'>' => {
-					result = Some(self.increment_pointer());
-					if self.memory_pointer > memory_pointer_max {
-						memory_pointer_max = self.memory_pointer;
+	// run a copy of the runtime from where it stands to estimate what the program needs, say to admit it under a
+	// quota: every input stream reads as endless zeros and the output is thrown away. This runtime is left as it
+	// was; the product's executions and peak_memory are the estimate, and the limits still apply to the copy,
+	// so set an execution_limit for programs that may never halt
+	pub fn dry_run(&self) -> RuntimeProduct<C> {
+		let mut runtime = Runtime::load_state(String::new(), self.save_state());
+		runtime.instructions = self.instructions.clone();
+		runtime.input = InputSource::Callback(Box::new(|| Some(0)));
+		for parked in runtime.parked_inputs.iter_mut() {
+			*parked = (InputSource::Callback(Box::new(|| Some(0))), 0);
+		}
+		runtime.validate_ascii_input = false; // the stand-in zeros would fail it, where real input might not
+
+		let mut product = runtime.run_observed(|_, _, _, _, _| true);
+		product.output.clear();
+		product
+	}
+
+	// run() for untrusted programs and restored state: a runtime whose pointers fall outside its memory, as a corrupt
+	// snapshot or saved state can produce, is refused with an error rather than left to panic on its first access
+	pub fn run_safe(&mut self) -> RuntimeProduct<C> {
+		if let Err(error) = self.check_state() {
+			self.error = Some(error);
+			return RuntimeProduct::new(self, 0, 0, Vec::new(), self.error);
+		}
+
+		self.run()
+	}
+
+	// the invariants every instruction handler relies on when indexing memory directly
+	fn check_state(&self) -> Result<(), RuntimeError> {
+		let length = self.memory.len();
+		let consistent = (length > 0)
+			&& (self.memory_pointer < length)
+			&& (self.memory_pointer_max < length)
+			&& (self.origin < length)
+			&& (self.active_input <= self.parked_inputs.len())
+			&& (self.instruction_pointer <= self.instructions.len());
+
+		if consistent { Ok(()) } else { Err(RuntimeError::CorruptState) }
+	}
+
+	// the run loop, with observe deciding what to keep from each execution and whether to carry on
+	fn run_observed<F>(&mut self, mut observe: F) -> RuntimeProduct<C>
+		where F: FnMut(&Runtime<C>, Op, usize, RuntimeResult, &mut Vec<RuntimeSnapshot<C>>) -> bool {
+		let start = self.clock.now_ns(); // start the stopwatch
+
+		let mut snapshots: Vec<RuntimeSnapshot<C>> = Vec::new();
+		let mut executions: usize = 0;
+		let mut aborted = false;
+		let mut snapshot_bytes = 0; // footprint of the snapshots counted so far, for the total memory limit
+		let mut counted = 0;
+
+		// compile up front unless already compiled, so unbalanced programs are rejected before anything executes
+		if let Err((offset, error)) = self.ensure_program() {
+			snapshots.push(RuntimeSnapshot::new(self, offset, Err(error))); // reported at the offending bracket
+			let time = self.elapsed_ns(start);
+			return RuntimeProduct::new(self, 0, time, snapshots, self.error);
+		}
+
+		if let Err(error) = self.check_input() {
+			self.error = Some(error);
+			snapshots.push(RuntimeSnapshot::new(self, self.instruction_pointer, Err(error)));
+			let time = self.elapsed_ns(start);
+			return RuntimeProduct::new(self, 0, time, snapshots, self.error);
+		}
+
+		while self.error.is_none() && self.current_instruction().is_some() {
+
+			// if the maximum number of instructions have already been executed
+			if (self.execution_limit > 0) && (executions >= self.execution_limit) {
+				return self.terminate(RuntimeError::ExecutionLimitExceeded, executions, start, snapshots);
+			}
+
+			// if the time budget has been spent; the clock is only read every so often to keep its cost down
+			if (self.time_limit_ns > 0) && executions.is_multiple_of(TIME_LIMIT_CHECK_INTERVAL) && (self.elapsed_ns(start) > self.time_limit_ns) {
+				return self.terminate(RuntimeError::TimeLimitExceeded, executions, start, snapshots);
+			}
+
+			if (self.total_memory_limit > 0) && (self.footprint() + snapshot_bytes > self.total_memory_limit) {
+				self.error = Some(RuntimeError::TotalMemoryLimitExceeded); // fatal, so a later run() doesn't carry on
+				return self.terminate(RuntimeError::TotalMemoryLimitExceeded, executions, start, snapshots);
+			}
+
+			if self.cancelled(executions) {
+				aborted = true;
+				break;
+			}
+
+			match self.execute() {
+				Some((instruction, position, result)) => {
+					executions += 1;
+					let keep_going = observe(self, instruction.op, position, result, &mut snapshots);
+					if self.total_memory_limit > 0 {
+						snapshot_bytes += snapshots[counted..].iter().map(RuntimeSnapshot::footprint).sum::<usize>();
+						counted = snapshots.len();
+					}
+					if !keep_going {
+						aborted = self.error.is_none() && self.current_instruction().is_some(); // stopping after the last instruction still completes
+						break;
 					}
 				},
-				'<' => result = Some(self.decrement_pointer()),
-				'+' => result = Some(self.increment_byte()),
-				'-' => result = Some(self.decrement_byte()),
-				'.' => result = Some(self.output_byte()),
-				',' => result = Some(self.input_byte()),
-				'[' => result = Some(self.handle_open_bracket()),
-				']' => result = Some(self.handle_close_bracket()),
-				_ => ()
-			}
-
-			if let Some(runtime_result) = result {
-				if runtime_result.is_ok() {
-					snapshots.push(RuntimeSnapshot::new(&self, memory_pointer_max, false, runtime_result.ok().unwrap()));
-				} else {
-					snapshots.push(RuntimeSnapshot::new(&self, memory_pointer_max, true, runtime_result.err().unwrap()));
-					break; // all errors are fatal
-				}
+				None => break
 			}
+		}
 
-			self.instruction_pointer += 1;
+		if let Err(error) = self.flush_output() {
+			self.error = self.error.or(Some(error));
 		}
 
-		let executions = snapshots.len();
-		RuntimeProduct::new(executions, (time::precise_time_ns() - start), self.output.clone(), snapshots)
+		let time = self.elapsed_ns(start);
+		let mut product = RuntimeProduct::new(self, executions, time, snapshots, self.error);
+		if aborted && product.error.is_none() {
+			product.termination = Termination::Aborted;
+		}
+		product
 	}
 
 }
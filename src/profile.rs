@@ -0,0 +1,67 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use compiler::Op;
+
+// how many times each kind of instruction executed; a folded op counts once per source instruction it stands for
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct InstructionProfile {
+	pub right: usize,
+	pub left: usize,
+	pub increment: usize,
+	pub decrement: usize,
+	pub output: usize,
+	pub input: usize,
+	pub open: usize,
+	pub close: usize,
+
+	pub clears: usize, // optimized clear loops, which stand for a data-dependent number of instructions, and '@'
+	pub multiplies: usize // fused multiply loops, likewise
+}
+
+impl InstructionProfile {
+
+	pub(crate) fn record(&mut self, op: Op) {
+		match op {
+			Op::Right(count) => self.right += count,
+			Op::Left(count) => self.left += count,
+			Op::Add(count) => self.increment += count,
+			Op::Sub(count) => self.decrement += count,
+			Op::Output => self.output += 1,
+			Op::Input => self.input += 1,
+			Op::Open(_) => self.open += 1,
+			Op::Close(_) => self.close += 1,
+			Op::Clear(_) | Op::Zero => self.clears += 1,
+			Op::Multiply(_) => self.multiplies += 1,
+			Op::Target(..) | Op::DebugDump | Op::SwitchInput => ()
+		}
+	}
+
+	// both profiles' counts added together
+	pub(crate) fn merge(&self, other: &InstructionProfile) -> InstructionProfile {
+		InstructionProfile {
+			right: self.right + other.right,
+			left: self.left + other.left,
+			increment: self.increment + other.increment,
+			decrement: self.decrement + other.decrement,
+			output: self.output + other.output,
+			input: self.input + other.input,
+			open: self.open + other.open,
+			close: self.close + other.close,
+
+			clears: self.clears + other.clears,
+			multiplies: self.multiplies + other.multiplies
+		}
+	}
+
+	// pointer movement, as opposed to arithmetic, I/O or control flow
+	pub fn movement(&self) -> usize {
+		self.right + self.left
+	}
+
+	pub fn arithmetic(&self) -> usize {
+		self.increment + self.decrement + self.clears + self.multiplies
+	}
+
+}
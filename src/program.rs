@@ -0,0 +1,109 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use binary::{Decoder, Encoder};
+use compiler::{self, Instruction};
+use {InstructionSet, Runtime, RuntimeError, RuntimeProduct};
+
+// a program parsed, checked and optimized once, to be run against any number of inputs without compiling it again
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Program {
+	instructions: Vec<u8>,
+	compiled: Vec<Instruction>
+}
+
+impl Program {
+
+	// standard Brainfuck with every optimization, failing like Runtime::validate() on an unbalanced program
+	pub fn compile(instructions: String) -> Result<Program, (usize, RuntimeError)> {
+		let instructions = instructions.into_bytes();
+		let compiled = compiler::compile(&instructions, &InstructionSet::standard(), &[], true, true, true, &[])?;
+		Ok(Program { instructions, compiled })
+	}
+
+	// a fresh runtime for input, ready to run without compiling; changing an option that affects compilation,
+	// such as the tape mode or the instruction set, makes it compile the source again on its next run
+	pub fn runtime(&self, input: Vec<u8>) -> Runtime {
+		let mut runtime = Runtime::new(String::new(), input);
+		runtime.instructions = self.instructions.clone();
+		runtime.optimize = true;
+		runtime.program = Some(self.compiled.clone());
+		runtime
+	}
+
+	pub fn run(&self, input: Vec<u8>, execution_limit: usize, memory_limit: usize) -> RuntimeProduct {
+		let mut runtime = self.runtime(input);
+		runtime.execution_limit = execution_limit;
+		runtime.memory_limit = memory_limit;
+		runtime.run()
+	}
+
+	// the program as a compact opcode stream for other tools: one byte per operation, 0 to 7 for > < + - . , [ ] in
+	// that order, with each run of > < + or - folded into one opcode followed by its length as a LEB128 varint
+	pub fn to_opcodes(&self) -> Vec<u8> {
+		let mut encoder = Encoder { bytes: Vec::new() };
+		let tokens = compiler::tokenize(&self.instructions, &InstructionSet::standard(), &[]);
+		let mut index = 0;
+		while index < tokens.len() {
+			let symbol = tokens[index].symbol;
+			let opcode = OPCODES.iter().position(|&known| known == symbol).unwrap_or(0);
+			encoder.bytes.push(opcode as u8);
+			index += 1;
+
+			if opcode < FOLDED_OPCODES {
+				let run = tokens[index..].iter().take_while(|token| token.symbol == symbol).count();
+				encoder.size(run + 1);
+				index += run;
+			}
+		}
+		encoder.bytes
+	}
+
+	// compile an opcode stream from to_opcodes() back into a program that behaves the same; the source is rebuilt
+	// with every folded run spelled out, up to MAX_SOURCE_LEN instructions in all. Fails with InvalidEncoding or
+	// UnmatchedBracket at the offset into opcodes of the first opcode that is wrong
+	pub fn from_opcodes(opcodes: &[u8]) -> Result<Program, (usize, RuntimeError)> {
+		let mut decoder = Decoder { bytes: opcodes, position: 0 };
+		let mut source = Vec::new();
+		let mut open = Vec::new(); // offsets into opcodes of the unclosed [s
+
+		while decoder.position < opcodes.len() {
+			let start = decoder.position;
+			let symbol = match OPCODES.get(opcodes[start] as usize) {
+				Some(&symbol) => symbol,
+				None => return Err((start, RuntimeError::InvalidEncoding))
+			};
+			decoder.position += 1;
+
+			match symbol {
+				b'[' => open.push(start),
+				b']' if open.pop().is_none() => return Err((start, RuntimeError::UnmatchedBracket)),
+				_ => ()
+			}
+
+			let count = if (opcodes[start] as usize) < FOLDED_OPCODES { decoder.size().map_err(|error| (start, error))? } else { 1 };
+			let length = match source.len().checked_add(count) {
+				Some(length) if (count > 0) && (length <= MAX_SOURCE_LEN) => length,
+				_ => return Err((start, RuntimeError::InvalidEncoding)) // an empty run, or one too long to spell out
+			};
+			source.resize(length, symbol);
+		}
+
+		if let Some(&start) = open.first() {
+			return Err((start, RuntimeError::UnmatchedBracket));
+		}
+
+		let compiled = compiler::compile(&source, &InstructionSet::standard(), &[], true, true, true, &[]).map_err(|(_, error)| (0, error))?;
+		Ok(Program { instructions: source, compiled })
+	}
+
+}
+
+// each opcode's instruction, indexed by opcode
+const OPCODES: [u8; 8] = [b'>', b'<', b'+', b'-', b'.', b',', b'[', b']'];
+
+// the longest source from_opcodes() spells out, so a few bytes of counts can't ask for an enormous allocation
+const MAX_SOURCE_LEN: usize = 1 << 24;
+
+// opcodes below this one are followed by a count
+const FOLDED_OPCODES: usize = 4;
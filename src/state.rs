@@ -0,0 +1,222 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use input::{random_byte, InputSource};
+use {Cell, EofBehavior, GrowthStrategy, InstructionProfile, InstructionSet, OutputMode, Runtime, RuntimeError, TapeMode};
+
+// everything needed to continue a run exactly where it stopped, unlike a snapshot, which keeps only
+// the touched memory and forgets limits and options; the program itself and any output sink are not included
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RuntimeState<C: Cell = u8> {
+	pub instruction_pointer: usize,
+	pub input: Vec<u8>, // the buffered input; empty for a streaming source, which has to be attached again after loading
+	pub input_pointer: usize,
+	pub parked_inputs: Vec<(Vec<u8>, usize)>, // the inactive input streams, buffered ones only, like input
+	pub active_input: usize,
+
+	pub memory: Vec<C>, // the whole tape, allocated cells past the touched region included
+	pub initial_memory: usize,
+	pub fill_value: u8,
+	pub initial_tape: Vec<C>,
+	pub memory_pointer: usize,
+	pub memory_pointer_max: usize,
+	pub origin: usize,
+
+	pub output: Vec<u8>,
+	pub output_written: usize,
+	pub output_mode: OutputMode,
+	pub consumed_input: Vec<u8>,
+
+	pub eof_behavior: EofBehavior,
+	pub validate_ascii_input: bool,
+	pub tape_mode: TapeMode,
+	pub grow_left: bool,
+	pub growth_strategy: GrowthStrategy,
+	pub signed_cells: bool,
+	pub record_snapshots: bool,
+	pub profile: bool,
+	pub instruction_profile: InstructionProfile,
+	pub value_histogram: Vec<u64>,
+	pub loop_iterations: BTreeMap<usize, u64>,
+	pub track_wraps: bool,
+	pub wraps: usize,
+	pub track_touched: bool,
+	pub touched: Vec<u64>,
+	pub time_instructions: bool,
+	pub instruction_times: Vec<u64>,
+	pub scanned_instructions: usize,
+	pub instruction_set: InstructionSet,
+	pub debug_dump: bool,
+	pub zero_cell: bool,
+	pub warnings_as_errors: bool,
+	pub strict_mode: bool,
+	pub error_dump: bool,
+	pub optimize: bool,
+
+	pub breakpoints: Vec<usize>,
+	pub paused: bool,
+	pub watchpoints: Vec<(usize, Option<C>)>,
+	pub error: Option<RuntimeError>,
+	pub recoverable: Vec<RuntimeError>,
+
+	pub execution_limit: usize,
+	pub memory_limit: usize,
+	pub output_limit: usize,
+	pub time_limit_ns: u64,
+	pub total_memory_limit: usize,
+	pub loop_iteration_limit: usize,
+	pub loop_passes: Vec<usize>,
+	pub max_program_len: usize,
+	pub max_instructions: usize
+}
+
+fn saved_input(input: &InputSource) -> Vec<u8> {
+	match *input {
+		InputSource::Buffer(ref buffer) => buffer.clone(),
+		InputSource::Random { seed, len } => (0..len).map(|position| random_byte(seed, position)).collect(), // restored as a buffer
+		_ => Vec::new() // a stream can't be saved
+	}
+}
+
+// checkpointing, for computations that have to outlive the process running them
+impl<C: Cell> Runtime<C> {
+
+	pub fn save_state(&self) -> RuntimeState<C> {
+		RuntimeState {
+			instruction_pointer: self.instruction_pointer,
+			input: saved_input(&self.input),
+			input_pointer: self.input_pointer,
+			parked_inputs: self.parked_inputs.iter().map(|&(ref input, input_pointer)| (saved_input(input), input_pointer)).collect(),
+			active_input: self.active_input,
+
+			memory: self.memory.clone(),
+			initial_memory: self.initial_memory,
+			fill_value: self.fill_value,
+			initial_tape: self.initial_tape.clone(),
+			memory_pointer: self.memory_pointer,
+			memory_pointer_max: self.memory_pointer_max,
+			origin: self.origin,
+
+			output: self.output.clone(),
+			output_written: self.output_written,
+			output_mode: self.output_mode,
+			consumed_input: self.consumed_input.clone(),
+
+			eof_behavior: self.eof_behavior,
+			validate_ascii_input: self.validate_ascii_input,
+			tape_mode: self.tape_mode,
+			grow_left: self.grow_left,
+			growth_strategy: self.growth_strategy,
+			signed_cells: self.signed_cells,
+			record_snapshots: self.record_snapshots,
+			profile: self.profile,
+			instruction_profile: self.instruction_profile,
+			value_histogram: self.value_histogram.clone(),
+			loop_iterations: self.loop_iterations.clone(),
+			track_wraps: self.track_wraps,
+			wraps: self.wraps,
+			track_touched: self.track_touched,
+			touched: self.touched.clone(),
+			time_instructions: self.time_instructions,
+			instruction_times: self.instruction_times.clone(),
+			scanned_instructions: self.scanned_instructions,
+			instruction_set: self.instruction_set.clone(),
+			debug_dump: self.debug_dump,
+			zero_cell: self.zero_cell,
+			warnings_as_errors: self.warnings_as_errors,
+			strict_mode: self.strict_mode,
+			error_dump: self.error_dump,
+			optimize: self.optimize,
+
+			breakpoints: self.breakpoints.clone(),
+			paused: self.paused,
+			watchpoints: self.watchpoints.clone(),
+			error: self.error,
+			recoverable: self.recoverable.clone(),
+
+			execution_limit: self.execution_limit,
+			memory_limit: self.memory_limit,
+			output_limit: self.output_limit,
+			time_limit_ns: self.time_limit_ns,
+			total_memory_limit: self.total_memory_limit,
+			loop_iteration_limit: self.loop_iteration_limit,
+			loop_passes: self.loop_passes.clone(),
+			max_program_len: self.max_program_len,
+			max_instructions: self.max_instructions
+		}
+	}
+
+	// resume from a saved state; instructions must be the program the state was saved from
+	pub fn load_state(instructions: String, state: RuntimeState<C>) -> Runtime<C> {
+		let mut runtime = Runtime::with_cell_size(instructions, state.input, state.execution_limit, state.memory_limit);
+
+		runtime.instruction_pointer = state.instruction_pointer;
+		runtime.input_pointer = state.input_pointer;
+		runtime.parked_inputs = state.parked_inputs.into_iter().map(|(input, input_pointer)| (InputSource::Buffer(input), input_pointer)).collect();
+		runtime.active_input = state.active_input;
+
+		runtime.memory = state.memory;
+		if runtime.memory.is_empty() {
+			runtime.memory.push(C::ZERO);
+		}
+		runtime.initial_memory = state.initial_memory;
+		runtime.fill_value = state.fill_value;
+		runtime.initial_tape = state.initial_tape;
+		runtime.memory_pointer = state.memory_pointer;
+		runtime.memory_pointer_max = state.memory_pointer_max;
+		runtime.origin = state.origin;
+
+		runtime.output = state.output;
+		runtime.output_written = state.output_written;
+		runtime.output_mode = state.output_mode;
+		runtime.consumed_input = state.consumed_input;
+
+		runtime.eof_behavior = state.eof_behavior;
+		runtime.validate_ascii_input = state.validate_ascii_input;
+		runtime.tape_mode = state.tape_mode;
+		runtime.grow_left = state.grow_left;
+		runtime.growth_strategy = state.growth_strategy;
+		runtime.signed_cells = state.signed_cells;
+		runtime.record_snapshots = state.record_snapshots;
+		runtime.profile = state.profile;
+		runtime.instruction_profile = state.instruction_profile;
+		runtime.value_histogram = state.value_histogram;
+		runtime.value_histogram.resize(256, 0); // one count per byte value, however the state was made
+		runtime.loop_iterations = state.loop_iterations;
+		runtime.track_wraps = state.track_wraps;
+		runtime.wraps = state.wraps;
+		runtime.track_touched = state.track_touched;
+		runtime.touched = state.touched;
+		runtime.time_instructions = state.time_instructions;
+		runtime.instruction_times = state.instruction_times;
+		runtime.scanned_instructions = state.scanned_instructions;
+		runtime.instruction_set = state.instruction_set;
+		runtime.debug_dump = state.debug_dump;
+		runtime.zero_cell = state.zero_cell;
+		runtime.warnings_as_errors = state.warnings_as_errors;
+		runtime.strict_mode = state.strict_mode;
+		runtime.error_dump = state.error_dump;
+		runtime.optimize = state.optimize;
+
+		runtime.breakpoints = state.breakpoints;
+		runtime.paused = state.paused;
+		runtime.watchpoints = state.watchpoints;
+		runtime.error = state.error;
+		runtime.recoverable = state.recoverable;
+
+		runtime.output_limit = state.output_limit;
+		runtime.time_limit_ns = state.time_limit_ns;
+		runtime.total_memory_limit = state.total_memory_limit;
+		runtime.loop_iteration_limit = state.loop_iteration_limit;
+		runtime.loop_passes = state.loop_passes;
+		runtime.max_program_len = state.max_program_len;
+		runtime.max_instructions = state.max_instructions;
+		runtime // compiled on the first instruction, which also lines op_pointer up with instruction_pointer
+	}
+
+}
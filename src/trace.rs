@@ -0,0 +1,139 @@
+use alloc::vec::Vec;
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use compiler::Op;
+use {Cell, Runtime, RuntimeSnapshot};
+
+// what one instruction changed, as opposed to a full copy of the state after it;
+// replaying deltas in order from a fresh tape reconstructs every snapshot
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct SnapshotDelta<C: Cell = u8> {
+	pub instruction_pointer: usize,
+	pub resume_pointer: usize, // offset execution continues from after this delta
+	pub pointer: isize, // logical pointer after the instruction, so indices stay stable when a bidirectional tape grows left
+	pub cells: Vec<(isize, C)>, // the logical index and new value of each cell whose value changed; only a fused loop changes several
+	pub output: Option<u8>, // the byte emitted by '.'
+
+	pub is_error: bool,
+	pub message: &'static str
+}
+
+// what changed from one snapshot to another, for showing a time-travel debugger's steps side by side
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct SnapshotDiff<C: Cell = u8> {
+	// the logical index and the values before and after of each cell that differs; a cell only one snapshot's tape
+	// covers, because the tape grew in between, reads as zero in the other
+	pub cells: Vec<(isize, C, C)>,
+	pub pointer_movement: isize, // how far the logical pointer moved, negative to the left
+	pub output: Vec<u8> // bytes output in between, assuming the later output carries on from the earlier
+}
+
+// executes the runtime one instruction per item, ending once the program has finished or failed
+pub struct Deltas<'a, C: Cell> {
+	runtime: &'a mut Runtime<C>
+}
+
+impl<'a, C: Cell> Iterator for Deltas<'a, C> {
+	type Item = SnapshotDelta<C>;
+
+	fn next(&mut self) -> Option<SnapshotDelta<C>> {
+		let runtime = &mut *self.runtime;
+		if runtime.error.is_none() {
+			if let Err((offset, error)) = runtime.ensure_program() { // written_cells() needs the compiled program
+				return Some(SnapshotDelta {
+					instruction_pointer: offset,
+					resume_pointer: runtime.instruction_pointer,
+					pointer: runtime.logical_pointer(),
+					cells: Vec::new(),
+					output: None,
+
+					is_error: true,
+					message: error.message()
+				});
+			}
+		}
+
+		let written: Vec<(isize, C)> = runtime.written_cells().into_iter().map(|index| (index, runtime.logical_cell(index))).collect();
+		let pointer = runtime.logical_pointer();
+
+		let (instruction, position, result) = runtime.execute()?;
+		let renumbered = match instruction.op {
+			Op::Multiply(_) => runtime.logical_pointer() - pointer, // grow_left moved the tape under a fused loop
+			_ => 0
+		};
+
+		Some(SnapshotDelta {
+			instruction_pointer: position,
+			resume_pointer: runtime.instruction_pointer,
+			pointer: runtime.logical_pointer(),
+			cells: written.into_iter()
+				.map(|(index, before)| (index + renumbered, before, runtime.logical_cell(index + renumbered)))
+				.filter(|&(_, before, after)| after != before)
+				.map(|(index, _, after)| (index, after))
+				.collect(),
+			output: match (instruction.op, result) {
+				(Op::Output, Ok(_)) => Some(runtime.current_cell().to_byte()),
+				_ => None
+			},
+
+			is_error: result.is_err(),
+			message: match result {
+				Ok(message) => message,
+				Err(error) => error.message()
+			}
+		})
+	}
+}
+
+impl<C: Cell> RuntimeSnapshot<C> {
+
+	// what changed from this snapshot to other, usually the one after it
+	pub fn diff(&self, other: &RuntimeSnapshot<C>) -> SnapshotDiff<C> {
+		let cell = |snapshot: &RuntimeSnapshot<C>, index: isize| {
+			let physical = index + snapshot.origin as isize;
+			if physical < 0 { C::ZERO } else { snapshot.memory.get(physical as usize).cloned().unwrap_or(C::ZERO) }
+		};
+
+		let start = -(self.origin.max(other.origin) as isize);
+		let end = (self.memory.len() as isize - self.origin as isize).max(other.memory.len() as isize - other.origin as isize);
+
+		SnapshotDiff {
+			cells: (start..end)
+				.map(|index| (index, cell(self, index), cell(other, index)))
+				.filter(|&(_, before, after)| before != after)
+				.collect(),
+			pointer_movement: other.logical_pointer() - self.logical_pointer(),
+			output: other.output.get(self.output.len()..).unwrap_or(&[]).to_vec()
+		}
+	}
+
+	// a copy for display without the zero cells at the high end of memory, which a program leaves behind when it
+	// visits a high index and comes back; the cells under the pointer and at the origin are always kept, so the copy
+	// still restores with Runtime::from_snapshot(), and this snapshot is untouched
+	pub fn trimmed(&self) -> RuntimeSnapshot<C> {
+		let length = self.memory.iter().rposition(|&cell| cell != C::ZERO).map_or(0, |last| last + 1);
+		let mut trimmed = self.clone();
+		trimmed.memory.truncate(length.max(self.memory_pointer + 1).max(self.origin + 1));
+		trimmed
+	}
+
+}
+
+// incremental tracing, for runs too long to snapshot in full
+impl<C: Cell> Runtime<C> {
+
+	// step through the program like step(), yielding only what each instruction changed;
+	// no limits are enforced, so cap the iterator with take() for programs that may not halt
+	pub fn deltas(&mut self) -> Deltas<'_, C> {
+		Deltas { runtime: self }
+	}
+
+	fn logical_pointer(&self) -> isize {
+		self.memory_pointer as isize - self.origin as isize
+	}
+
+}
@@ -0,0 +1,42 @@
+extern crate forkengine;
+
+use forkengine::{run_batch, score_output, BatchLimits, OutputMetric, RuntimeError};
+
+#[test]
+fn products_come_back_in_program_order() {
+	let programs: Vec<(String, Vec<u8>)> = (0..40u8)
+		.map(|n| (format!(",{}.", "+".repeat(n as usize)), vec![b'a']))
+		.chain(Some(("+[]".to_string(), Vec::new())))
+		.collect();
+
+	for &threads in &[1, 4] {
+		let limits = BatchLimits { execution_limit: 1000, threads, ..BatchLimits::default() };
+		let products = run_batch(programs.clone(), limits);
+
+		assert_eq!(products.len(), 41);
+		for (n, product) in products[..40].iter().enumerate() {
+			assert_eq!(product.error, None);
+			assert_eq!(product.output, vec![b'a' + n as u8]);
+			assert!(product.snapshots.is_empty());
+		}
+		assert_eq!(products[40].error, Some(RuntimeError::ExecutionLimitExceeded));
+	}
+}
+
+#[test]
+fn output_is_scored_against_the_target() {
+	let products = run_batch(vec![
+		("+++[>++++++++++<-]>+++.+.+.".to_string(), Vec::new()), // "!\"#"
+		("+++[>++++++++++<-]>+++.++.+.".to_string(), Vec::new()), // "!#$"
+		("+++[>++++++++++<-]>+++.+.+.[]".to_string(), Vec::new()) // "!\"#", then stuck
+	], BatchLimits { execution_limit: 200, ..BatchLimits::default() });
+	let expected = b"\"#$";
+
+	assert_eq!(score_output(&products[0], b"!\"#", OutputMetric::Hamming), 1.0);
+	assert_eq!(score_output(&products[0], b"!\"#", OutputMetric::Levenshtein), 1.0);
+	assert_eq!(score_output(&products[2], b"!\"#", OutputMetric::Hamming), 0.75); // never finished
+
+	assert_eq!(score_output(&products[0], expected, OutputMetric::Hamming), 0.0);
+	assert_eq!(score_output(&products[0], expected, OutputMetric::Levenshtein), 1.0 / 3.0); // one insertion and one deletion
+	assert_eq!(score_output(&products[1], expected, OutputMetric::Hamming), 2.0 / 3.0);
+}
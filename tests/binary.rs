@@ -0,0 +1,60 @@
+extern crate forkengine;
+
+use forkengine::{EofBehavior, RuntimeBuilder, RuntimeError, RuntimeProduct, TapeMode};
+
+const HELLO: &str = "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.";
+
+// between them these hit every kind of snapshot message, so any the decoder doesn't know fails here
+fn products() -> Vec<RuntimeProduct> {
+	let mut products = Vec::new();
+	for &optimize in &[false, true] {
+		let builder = || RuntimeBuilder::new().optimize(optimize).profile(true).track_wraps(true).time_instructions(true);
+
+		products.push(builder().instructions(HELLO.to_string()).build().run());
+		products.push(builder().instructions("-+[-]+++[>+<-]<".to_string()).build().run());
+		products.push(builder().instructions("+[]".to_string()).execution_limit(10).build().run());
+		products.push(builder().instructions("[[]".to_string()).build().run());
+		products.push(builder().instructions(">>><<<<".to_string()).tape_mode(TapeMode::FixedWrapping(2)).build().run());
+		products.push(builder().instructions(">>><<<<".to_string()).tape_mode(TapeMode::FixedClamped(2)).build().run());
+		products.push(builder().instructions("<<+>>".to_string()).tape_mode(TapeMode::Bidirectional).build().run());
+		products.push(builder().instructions("-+-++".to_string()).signed_cells(true).build().run());
+		products.push(builder().instructions(".,,".to_string()).input(vec![7]).output_limit(1).build().run());
+		products.push(builder().instructions(",,".to_string()).eof_behavior(EofBehavior::Zero).build().run());
+		products.push(builder().instructions(",,".to_string()).eof_behavior(EofBehavior::Unchanged).build().run());
+		products.push(builder().instructions(",,".to_string()).eof_value(10).build().run());
+		products.push(builder().instructions(",;,#".to_string()).input_stream(vec![1]).debug_dump(true).build().run());
+		#[cfg(feature = "std")]
+		products.push(builder().instructions("+.".to_string()).output(Vec::new()).build().run());
+		products.push(builder().instructions("+@".to_string()).zero_cell(true).build().run());
+		products.push(builder().instructions("+[>+]".to_string()).total_memory_limit(4096).build().run());
+		products.push(builder().instructions("+[>+.<]".to_string()).loop_iteration_limit(3).build().run());
+		products.push(builder().instructions("+>>+<<<+".to_string()).tape_mode(TapeMode::Bidirectional).track_touched(true).build().run());
+	}
+	products
+}
+
+#[test]
+fn products_round_trip() {
+	for product in products() {
+		assert_eq!(RuntimeProduct::from_bytes(&product.to_bytes()), Ok(product));
+	}
+}
+
+#[test]
+fn wide_cells_round_trip() {
+	let product = RuntimeBuilder::<u16>::default().instructions("-.>+++[>+++<-]".to_string()).build().run();
+	assert_eq!(RuntimeProduct::<u16>::from_bytes(&product.to_bytes()), Ok(product.clone()));
+	assert_eq!(RuntimeProduct::<u8>::from_bytes(&product.to_bytes()), Err(RuntimeError::InvalidEncoding)); // different cell width
+}
+
+#[test]
+fn truncated_or_padded_bytes_are_rejected() {
+	let bytes = RuntimeBuilder::new().instructions("++[>+<-]>.".to_string()).profile(true).build().run().to_bytes();
+	for length in 0..bytes.len() {
+		assert_eq!(RuntimeProduct::<u8>::from_bytes(&bytes[..length]), Err(RuntimeError::InvalidEncoding));
+	}
+
+	let mut padded = bytes.clone();
+	padded.push(0);
+	assert_eq!(RuntimeProduct::<u8>::from_bytes(&padded), Err(RuntimeError::InvalidEncoding));
+}
@@ -0,0 +1,60 @@
+extern crate forkengine;
+
+use forkengine::{RuntimeBuilder, RuntimeError, RuntimeProduct};
+
+// deeper than a u16 bracket counter could track
+const DEPTH: usize = 70_000;
+
+fn run(instructions: String) -> RuntimeProduct {
+	RuntimeBuilder::new()
+		.instructions(instructions)
+		.record_snapshots(false)
+		.build()
+		.run()
+}
+
+#[test]
+fn skipping_a_deep_nest_lands_after_its_match() {
+	let program = format!("{}{}+.", "[".repeat(DEPTH), "]".repeat(DEPTH));
+
+	let product = run(program);
+	assert_eq!(product.error, None);
+	assert_eq!(product.output, vec![1]);
+	assert_eq!(product.executions, 3); // the outermost '[' jumps straight past every ']'
+}
+
+#[test]
+fn entering_a_deep_nest_matches_every_pair() {
+	let program = format!("+{}-{}.", "[".repeat(DEPTH), "]".repeat(DEPTH));
+
+	let product = run(program);
+	assert_eq!(product.error, None);
+	assert_eq!(product.output, vec![0]);
+	assert_eq!(product.executions, 1 + DEPTH + 1 + DEPTH + 1);
+}
+
+#[test]
+fn deep_nest_missing_one_close_is_unmatched() {
+	let program = format!("{}{}", "[".repeat(DEPTH), "]".repeat(DEPTH - 1));
+
+	let product = run(program);
+	assert_eq!(product.error, Some(RuntimeError::UnmatchedBracket));
+	assert_eq!(product.executions, 0);
+}
+
+#[test]
+fn loop_iterations_are_counted_by_opening_bracket() {
+	for &optimize in &[false, true] {
+		let product = RuntimeBuilder::new()
+			.instructions("+++[>++[-]<-] ++[>.<-]".to_string())
+			.optimize(optimize)
+			.profile(true)
+			.build()
+			.run();
+
+		let loops = product.loop_iterations.unwrap();
+		assert_eq!(loops.get(&3), Some(&2)); // entered once, three passes through the body
+		assert_eq!(loops.get(&16), Some(&1));
+		assert_eq!(loops.get(&7), if optimize { None } else { Some(&3) }); // [-] is fused into a clear when optimized
+	}
+}
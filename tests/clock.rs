@@ -0,0 +1,28 @@
+extern crate forkengine;
+
+use forkengine::{MockClock, RuntimeBuilder, RuntimeError};
+
+#[test]
+fn elapsed_time_comes_from_the_clock() {
+	let mut runtime = RuntimeBuilder::new()
+		.instructions("+++.".to_string())
+		.clock(MockClock::new(1000, 250))
+		.build();
+
+	let product = runtime.run();
+	assert_eq!(product.error, None);
+	assert_eq!(product.time, 250); // read once at the start and once at the end
+}
+
+#[test]
+fn time_limit_is_checked_against_the_clock() {
+	let mut runtime = RuntimeBuilder::new()
+		.instructions("+[]".to_string())
+		.time_limit_ns(1500)
+		.clock(MockClock::new(0, 1000))
+		.build();
+
+	let product = runtime.run();
+	assert_eq!(product.error, Some(RuntimeError::TimeLimitExceeded));
+	assert_eq!(product.executions, 1024); // 1000ns in at the first check, 2000ns at the second
+}
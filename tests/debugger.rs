@@ -0,0 +1,21 @@
+extern crate forkengine;
+
+use forkengine::{Pause, RuntimeBuilder, RuntimeError};
+
+#[test]
+fn a_program_that_never_pauses_stops_at_the_execution_limit() {
+	let mut runtime = RuntimeBuilder::new()
+		.instructions("+[]".to_string())
+		.execution_limit(100)
+		.build();
+
+	for _ in 0..2 { // each call gets the whole limit, and the program carries on where it was
+		match runtime.run_until_break() {
+			Pause::Finished(Some(snapshot)) => {
+				assert!(snapshot.is_error);
+				assert_eq!(snapshot.message, RuntimeError::ExecutionLimitExceeded.message());
+			},
+			_ => panic!("expected the execution limit to finish the call")
+		}
+	}
+}
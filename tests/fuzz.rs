@@ -0,0 +1,103 @@
+extern crate forkengine;
+
+use forkengine::{EofBehavior, Runtime, RuntimeBuilder, RuntimeError, TapeMode};
+
+// xorshift64, so the test needs no dependencies and every failure reproduces from its seed
+struct Rng(u64);
+
+impl Rng {
+	fn next(&mut self) -> u64 {
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 7;
+		self.0 ^= self.0 << 17;
+		self.0
+	}
+
+	fn below(&mut self, bound: usize) -> usize {
+		(self.next() % bound as u64) as usize
+	}
+}
+
+// mostly instructions, so programs actually do something, with arbitrary bytes mixed in as comments
+fn random_program(rng: &mut Rng) -> String {
+	let length = rng.below(200);
+	let bytes: Vec<u8> = (0..length).map(|_| match rng.below(10) {
+		0 => rng.below(128) as u8,
+		_ => b"><+-.,[]"[rng.below(8)]
+	}).collect();
+
+	String::from_utf8(bytes).unwrap()
+}
+
+fn random_tape_mode(rng: &mut Rng) -> TapeMode {
+	match rng.below(4) {
+		0 => TapeMode::Growable,
+		1 => TapeMode::FixedWrapping(1 + rng.below(16)),
+		2 => TapeMode::FixedClamped(1 + rng.below(16)),
+		_ => TapeMode::Bidirectional
+	}
+}
+
+#[test]
+fn random_programs_never_panic() {
+	let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+
+	for _ in 0..2_000 {
+		let program = random_program(&mut rng);
+		let input: Vec<u8> = (0..rng.below(8)).map(|_| rng.next() as u8).collect();
+		let tape_mode = random_tape_mode(&mut rng);
+		let memory_limit = rng.below(64);
+
+		let build = |optimize: bool| RuntimeBuilder::new()
+			.instructions(program.clone())
+			.input(input.clone())
+			.execution_limit(5_000)
+			.memory_limit(memory_limit)
+			.output_limit(64)
+			.eof_behavior(EofBehavior::Zero)
+			.tape_mode(tape_mode)
+			.record_snapshots(false)
+			.optimize(optimize)
+			.build();
+
+		let naive = build(false).run_safe();
+		let optimized = build(true).run_safe();
+
+		// the execution limit counts folded ops once, so only runs that finished are comparable
+		if naive.error.is_none() && optimized.error.is_none() {
+			assert_eq!(naive.output, optimized.output, "program {:?}", program);
+		}
+	}
+}
+
+#[test]
+fn scrambled_states_never_panic() {
+	let mut rng = Rng(0xD1B5_4A32_D192_ED03);
+
+	for _ in 0..500 {
+		let program = random_program(&mut rng);
+		let mut runtime = RuntimeBuilder::new()
+			.instructions(program.clone())
+			.execution_limit(1 + rng.below(500))
+			.eof_behavior(EofBehavior::Zero)
+			.record_snapshots(false)
+			.build();
+		runtime.run();
+
+		// scramble the pointers, as a corrupt or hand-edited checkpoint might
+		let mut state = runtime.save_state();
+		let length = state.memory.len();
+		state.memory_pointer = rng.below(2 * length);
+		state.memory_pointer_max = rng.below(2 * length);
+		state.origin = rng.below(2 * length);
+		state.instruction_pointer = rng.below(program.len() + 2);
+
+		let corrupt = (state.memory_pointer >= length) || (state.memory_pointer_max >= length) || (state.origin >= length)
+			|| (state.instruction_pointer > program.len());
+
+		let product = Runtime::load_state(program, state).run_safe();
+		if corrupt {
+			assert_eq!(product.error, Some(RuntimeError::CorruptState));
+		}
+	}
+}
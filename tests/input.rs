@@ -0,0 +1,106 @@
+extern crate forkengine;
+
+use forkengine::{EofBehavior, Runtime, RuntimeBuilder, RuntimeError};
+
+#[test]
+fn non_ascii_input_is_rejected_before_running() {
+	let product = RuntimeBuilder::new()
+		.instructions("+,.".to_string())
+		.input(b"ok\n\xff".to_vec())
+		.validate_ascii_input(true)
+		.build()
+		.run();
+	assert_eq!(product.error, Some(RuntimeError::NonAsciiInput(0xff, 3)));
+	assert_eq!(product.executions, 0);
+
+	let product = RuntimeBuilder::new().instructions(",.".to_string()).input(vec![0xff]).build().run();
+	assert_eq!(product.error, None); // binary input is fine unless validation is on
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn non_ascii_stream_input_is_rejected_as_it_is_read() {
+	let product = RuntimeBuilder::new()
+		.instructions(",.,.".to_string())
+		.input_reader(&b"a\x01"[..])
+		.validate_ascii_input(true)
+		.build()
+		.run();
+	assert_eq!(product.error, Some(RuntimeError::NonAsciiInput(0x01, 1))); // a stream is only checked as it is read
+	assert_eq!(product.output, b"a");
+}
+
+#[test]
+fn replay_reproduces_a_streamed_run() {
+	let program = ",[.,]+[>,.<-]".to_string(); // the second loop reads past the end of the input
+	let original = RuntimeBuilder::new()
+		.instructions(program.clone())
+		.input_callback({
+			let mut bytes = b"echo".to_vec().into_iter();
+			move || bytes.next()
+		})
+		.eof_behavior(EofBehavior::Zero)
+		.build()
+		.run();
+	assert_eq!(original.input, b"echo");
+
+	let mut replay = Runtime::replay(program, &original);
+	replay.set_eof_behavior(EofBehavior::Zero); // options are not carried over
+	let replayed = replay.run();
+	assert_eq!(replayed.output, original.output);
+	assert_eq!(replayed.executions, original.executions);
+	assert_eq!(replayed.input, original.input);
+}
+
+#[test]
+fn eof_value_is_stored_once_input_runs_out() {
+	let mut runtime = RuntimeBuilder::<u16>::default().instructions(",>,".to_string()).input(vec![7]).eof_value(10).build();
+	runtime.run();
+	assert_eq!(runtime.current_cell(), 10); // not widened to the cell's maximum
+}
+
+#[test]
+fn random_input_is_reproducible_from_its_seed() {
+	let run = |seed: u64| {
+		let mut runtime = RuntimeBuilder::new()
+			.instructions(",[.,]".to_string())
+			.random_input(seed, 64)
+			.eof_behavior(EofBehavior::Zero)
+			.execution_limit(1000)
+			.build();
+		let output = runtime.run().output;
+		runtime.reset();
+		assert_eq!(runtime.run().output, output);
+		output
+	};
+
+	let output = run(7);
+	assert!(output.len() <= 64); // a zero byte ends the loop early
+	assert_eq!(run(7), output);
+	assert_ne!(run(8), output);
+}
+
+#[test]
+fn a_restored_stream_index_past_the_streams_is_refused() {
+	let mut state = RuntimeBuilder::new().instructions(",.".to_string()).build().save_state();
+	state.active_input = 3; // there are no other streams
+
+	let mut runtime = Runtime::load_state(",.".to_string(), state);
+	assert_eq!(runtime.run_safe().error, Some(RuntimeError::CorruptState));
+
+	runtime.reset(); // back to the first stream rather than rotating forever
+	assert_eq!(runtime.active_input(), 0);
+}
+
+#[test]
+fn a_dry_run_ignores_ascii_validation() {
+	let runtime = RuntimeBuilder::new()
+		.instructions(",.".to_string())
+		.input(b"a".to_vec())
+		.validate_ascii_input(true)
+		.build();
+
+	let product = runtime.dry_run();
+	assert_eq!(product.error, None);
+	assert_eq!(product.executions, 2);
+}
@@ -0,0 +1,115 @@
+extern crate forkengine;
+
+use forkengine::{Runtime, RuntimeBuilder, RuntimeError, Termination};
+
+#[test]
+fn limit_of_one_executes_exactly_one_instruction() {
+	let mut runtime = RuntimeBuilder::new()
+		.instructions("+++".to_string())
+		.execution_limit(1)
+		.build();
+
+	let product = runtime.run();
+	assert_eq!(product.executions, 1);
+	assert_eq!(product.termination, Termination::ExecutionLimit);
+	assert_eq!(product.error, Some(RuntimeError::ExecutionLimitExceeded));
+	assert_eq!(product.snapshots.len(), 2); // the instruction, then the refusal, which is not counted
+	assert!(product.snapshots[1].is_error);
+	assert_eq!(runtime.current_cell(), 1);
+
+	let product = runtime.run(); // each run gets the full limit again
+	assert_eq!(product.executions, 1);
+	assert_eq!(runtime.current_cell(), 2);
+}
+
+#[test]
+fn refusal_is_not_counted_without_snapshots() {
+	let mut runtime = RuntimeBuilder::new()
+		.instructions("+++".to_string())
+		.execution_limit(1)
+		.record_snapshots(false)
+		.build();
+
+	let product = runtime.run();
+	assert_eq!(product.executions, 1);
+	assert!(product.snapshots.is_empty());
+	assert_eq!(product.termination, Termination::ExecutionLimit);
+}
+
+#[test]
+fn program_exactly_at_the_limit_completes() {
+	let mut runtime = RuntimeBuilder::new()
+		.instructions("+".to_string())
+		.execution_limit(1)
+		.build();
+
+	let product = runtime.run();
+	assert_eq!(product.executions, 1);
+	assert_eq!(product.termination, Termination::Completed);
+	assert_eq!(product.snapshots.len(), 1);
+}
+
+#[test]
+fn total_memory_limit_counts_snapshots_and_output() {
+	let mut runtime = RuntimeBuilder::new()
+		.instructions("+[.]".to_string())
+		.total_memory_limit(64 * 1024)
+		.build();
+
+	let product = runtime.run();
+	assert_eq!(product.error, Some(RuntimeError::TotalMemoryLimitExceeded));
+	assert_eq!(product.termination, Termination::Error(RuntimeError::TotalMemoryLimitExceeded));
+	assert!(product.executions > 0);
+	assert_eq!(runtime.run().executions, 0); // fatal, so nothing more runs
+
+	let product = RuntimeBuilder::new()
+		.instructions("+[.]".to_string())
+		.record_snapshots(false)
+		.total_memory_limit(64 * 1024)
+		.build()
+		.run();
+	assert_eq!(product.error, Some(RuntimeError::TotalMemoryLimitExceeded));
+	assert!(product.output.len() <= 64 * 1024); // only the output counts now, so far more of it fits
+	assert!(product.output.len() > 32 * 1024);
+}
+
+#[test]
+fn loop_iteration_limit_names_the_runaway_loop() {
+	for &optimize in &[false, true] {
+		let builder = || RuntimeBuilder::new().optimize(optimize).loop_iteration_limit(5);
+
+		// the inner loop makes four passes each time, and is entered afresh on each of the outer loop's five
+		let product = builder().instructions("+++++[>++++[>+<-]<-]".to_string()).build().run();
+		assert_eq!(product.error, None);
+
+		let product = builder().instructions("++++++[>+.<-]".to_string()).build().run();
+		assert_eq!(product.error, Some(RuntimeError::LoopLimitExceeded(6))); // six passes is one too many
+
+		let product = builder().instructions("+++++[-]>+[+++]".to_string()).build().run();
+		assert_eq!(product.error, Some(RuntimeError::LoopLimitExceeded(10)));
+		assert!(product.snapshots.last().unwrap().is_error);
+
+		let product = builder().instructions("++++++++++++[-]".to_string()).build().run();
+		assert_eq!(product.error, Some(RuntimeError::LoopLimitExceeded(12))); // counted even where it could be fused
+	}
+}
+
+#[test]
+fn loop_iteration_limit_survives_a_saved_state() {
+	for &optimize in &[false, true] {
+		let program = "++++++[>+.<-]".to_string();
+		let mut runtime = RuntimeBuilder::new().instructions(program.clone()).optimize(optimize).loop_iteration_limit(5).build();
+		assert_eq!(runtime.run_until_output(3).error, None); // paused on the loop's third pass
+
+		let product = Runtime::load_state(program, runtime.save_state()).run();
+		assert_eq!(product.error, Some(RuntimeError::LoopLimitExceeded(6))); // still the sixth pass, not the third
+		assert_eq!(product.output, vec![1, 2, 3, 4, 5]);
+	}
+}
+
+#[test]
+fn headless_runs_stop_at_the_execution_limit() {
+	let (output, executions, _) = RuntimeBuilder::new().instructions("+[.]".to_string()).execution_limit(7).build().run_headless();
+	assert_eq!(executions, 7);
+	assert_eq!(output, vec![1, 1, 1]);
+}
@@ -0,0 +1,42 @@
+extern crate forkengine;
+
+use forkengine::{Lint, LintCategory, RuntimeBuilder, TapeMode};
+
+fn lints(instructions: &str) -> Vec<Lint> {
+	RuntimeBuilder::<u8>::new().instructions(instructions.to_string()).build().lint()
+}
+
+#[test]
+fn suspect_constructs_are_found_in_source_order() {
+	assert_eq!(lints("<+-.++,.> +[-]>"), vec![
+		Lint { offset: 0, category: LintCategory::LeadingUnderflow },
+		Lint { offset: 1, category: LintCategory::DeadArithmetic },
+		Lint { offset: 4, category: LintCategory::DeadArithmetic },
+		Lint { offset: 14, category: LintCategory::UnusedMove }
+	]);
+	assert_eq!(lints("+>>comment<<."), vec![Lint { offset: 1, category: LintCategory::UnusedMove }]);
+	assert_eq!(lints("++[>+<-]>."), Vec::new());
+}
+
+#[test]
+fn a_bidirectional_tape_can_start_leftward() {
+	let runtime = RuntimeBuilder::<u8>::new()
+		.instructions("<+.".to_string())
+		.tape_mode(TapeMode::Bidirectional)
+		.build();
+
+	assert!(runtime.lint().is_empty());
+}
+
+#[test]
+fn enabled_extensions_are_instructions_not_comments() {
+	let runtime = RuntimeBuilder::<u8>::new()
+		.instructions("+#@x".to_string())
+		.debug_dump(true)
+		.zero_cell(true)
+		.build();
+
+	assert_eq!(runtime.disassemble(), vec![(0, '+'), (1, '#'), (2, '@')]);
+	assert_eq!(runtime.comments(), vec![(3, "x".to_string())]);
+	assert_eq!(runtime.instruction_count(), 3);
+}
@@ -0,0 +1,190 @@
+extern crate forkengine;
+
+use forkengine::{OutputMode, RuntimeBuilder, RuntimeError};
+
+#[test]
+fn last_cell_under_a_tight_limit_is_reachable() {
+	for &optimize in &[false, true] {
+		let mut runtime = RuntimeBuilder::new()
+			.instructions(">>>>+".to_string())
+			.memory_limit(5)
+			.optimize(optimize)
+			.build();
+
+		let product = runtime.run();
+		assert_eq!(product.error, None);
+		assert_eq!(runtime.memory_pointer(), 4);
+		assert_eq!(runtime.current_cell(), 1);
+		assert_eq!(product.peak_memory, 4);
+	}
+}
+
+#[test]
+fn stepping_past_the_limit_is_refused() {
+	for &optimize in &[false, true] {
+		let mut runtime = RuntimeBuilder::new()
+			.instructions(">>>>>".to_string())
+			.memory_limit(5)
+			.optimize(optimize)
+			.build();
+
+		let product = runtime.run();
+		assert_eq!(product.error, Some(RuntimeError::MemoryLimitExceeded));
+		assert_eq!(runtime.memory_pointer(), 4);
+		assert_eq!(runtime.memory_slice().len(), 5);
+	}
+}
+
+#[test]
+fn limit_of_one_allows_only_the_first_cell() {
+	let mut runtime = RuntimeBuilder::new()
+		.instructions("+>".to_string())
+		.memory_limit(1)
+		.build();
+
+	let product = runtime.run();
+	assert_eq!(product.error, Some(RuntimeError::MemoryLimitExceeded));
+	assert_eq!(runtime.memory_pointer(), 0);
+	assert_eq!(runtime.current_cell(), 1);
+}
+
+#[test]
+fn grown_cells_read_as_zero() {
+	for &optimize in &[false, true] {
+		let mut runtime = RuntimeBuilder::new()
+			.instructions("+[>.+]".to_string()) // prints every cell it moves onto before marking it, stopping at the limit
+			.memory_limit(100)
+			.optimize(optimize)
+			.build();
+
+		let product = runtime.run();
+		assert_eq!(product.error, Some(RuntimeError::MemoryLimitExceeded));
+		assert_eq!(product.output, vec![0; 99]);
+		assert_eq!(runtime.memory_slice().len(), 100);
+		assert!(runtime.memory_slice().iter().all(|&cell| cell == 1));
+	}
+}
+
+#[test]
+fn pointer_never_passes_the_tape_length() {
+	let mut runtime = RuntimeBuilder::new()
+		.instructions(">>>>>>>>>>".to_string())
+		.initial_memory(3)
+		.build();
+
+	while runtime.step().is_some() {
+		assert!(runtime.memory_pointer() < runtime.memory_slice().len());
+	}
+	assert_eq!(runtime.memory_pointer(), 10);
+}
+
+#[test]
+fn error_dump_keeps_the_whole_tape() {
+	let mut runtime = RuntimeBuilder::new()
+		.instructions("+.<".to_string())
+		.initial_memory(8)
+		.record_snapshots(false)
+		.error_dump(true)
+		.build();
+
+	let product = runtime.run();
+	assert_eq!(product.error, Some(RuntimeError::PointerUnderflow));
+	assert_eq!(product.snapshots.len(), 1); // only the fatal error, since recording is off
+	assert_eq!(product.snapshots[0].memory, vec![1, 0, 0, 0, 0, 0, 0, 0]);
+	assert_eq!(product.snapshots[0].output, vec![1]);
+}
+
+#[test]
+fn ring_output_keeps_only_the_tail() {
+	let mut runtime = RuntimeBuilder::new()
+		.instructions("+[.+]".to_string())
+		.output_mode(OutputMode::Ring(10))
+		.build();
+
+	let product = runtime.run();
+	assert_eq!(product.output, (246..=255).collect::<Vec<u8>>());
+	assert_eq!(runtime.output_len(), 255);
+	assert!(product.snapshots.iter().all(|snapshot| snapshot.output.len() <= 10));
+}
+
+#[test]
+fn touched_cells_are_the_ones_written() {
+	for &optimize in &[false, true] {
+		let product = RuntimeBuilder::new()
+			.instructions("+>>>[.][-]>>+<<<<<<+".to_string()) // neither loop runs, and the last + grows the tape left
+			.tape_mode(forkengine::TapeMode::Bidirectional)
+			.optimize(optimize)
+			.track_touched(true)
+			.build()
+			.run();
+
+		let origin = product.snapshots.last().unwrap().origin;
+		let touched = product.touched_cells().unwrap();
+		assert_eq!(touched, vec![origin - 1, origin, origin + 5]);
+	}
+}
+
+#[test]
+fn fill_value_seeds_initial_and_grown_cells() {
+	for &optimize in &[false, true] {
+		let mut runtime = RuntimeBuilder::new()
+			.instructions(".>>>>>>>>>.+.".to_string())
+			.fill_value(7)
+			.optimize(optimize)
+			.build();
+
+		let product = runtime.run();
+		assert_eq!(product.error, None);
+		assert_eq!(product.output, vec![7, 7, 8]);
+
+		runtime.reset();
+		assert_eq!(runtime.current_cell(), 7);
+	}
+}
+
+#[test]
+fn growing_left_renumbers_the_tape() {
+	for &optimize in &[false, true] {
+		let mut runtime = RuntimeBuilder::new()
+			.instructions("++<<+++>>[<<+>>-]".to_string())
+			.grow_left(true)
+			.record_snapshots(true)
+			.optimize(optimize)
+			.build();
+
+		let product = runtime.run();
+		assert_eq!(product.error, None);
+		assert_eq!(runtime.memory_pointer(), 2);
+		assert_eq!(runtime.memory_slice(), &[5, 0, 0]);
+
+		let last = product.snapshots.last().unwrap();
+		assert_eq!(last.memory_pointer, 2);
+		assert_eq!(last.origin, 0);
+	}
+}
+
+#[test]
+fn growing_left_under_a_fused_loop_keeps_written_cells() {
+	let mut runtime = RuntimeBuilder::new()
+		.instructions("+[<+>-]".to_string())
+		.grow_left(true)
+		.optimize(true)
+		.track_touched(true)
+		.profile(true)
+		.build();
+
+	let product = runtime.run();
+	assert_eq!(product.error, None);
+	assert_eq!(runtime.memory_slice(), &[1, 0]);
+	assert_eq!(product.touched_cells(), Some(vec![0, 1]));
+	assert_eq!(product.value_histogram.unwrap()[1], 2); // the counter's '+' and the target
+
+	let mut runtime = RuntimeBuilder::new()
+		.instructions("+[<+>-]".to_string())
+		.grow_left(true)
+		.optimize(true)
+		.build();
+	let fused = runtime.deltas().nth(1).unwrap();
+	assert_eq!(fused.pointer, 1);
+	assert_eq!(fused.cells, vec![(1, 0), (0, 1)]);
+}
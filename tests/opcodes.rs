@@ -0,0 +1,47 @@
+extern crate forkengine;
+
+use forkengine::{Program, RuntimeError};
+
+const HELLO: &str = "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.";
+
+#[test]
+fn opcodes_round_trip() {
+	let program = Program::compile(format!("hello: {}", HELLO)).unwrap();
+	let opcodes = program.to_opcodes();
+	assert_eq!(&opcodes[..4], &[2, 8, 6, 0]); // eight +, then [ and a single >
+
+	let decoded = Program::from_opcodes(&opcodes).unwrap();
+	assert_eq!(decoded.to_opcodes(), opcodes);
+	assert_eq!(decoded.run(Vec::new(), 0, 0).output, b"Hello World!\n");
+	assert_eq!(decoded.run(Vec::new(), 0, 0).executions, program.run(Vec::new(), 0, 0).executions);
+}
+
+#[test]
+fn malformed_opcodes_are_rejected() {
+	assert_eq!(Program::from_opcodes(&[8]), Err((0, RuntimeError::InvalidEncoding))); // no such opcode
+	assert_eq!(Program::from_opcodes(&[2, 0]), Err((0, RuntimeError::InvalidEncoding))); // empty run
+	assert_eq!(Program::from_opcodes(&[4, 2]), Err((1, RuntimeError::InvalidEncoding))); // count missing
+	assert_eq!(Program::from_opcodes(&[6, 6, 7]), Err((0, RuntimeError::UnmatchedBracket)));
+	assert_eq!(Program::from_opcodes(&[7]), Err((0, RuntimeError::UnmatchedBracket)));
+}
+
+#[test]
+fn oversized_counts_are_rejected() {
+	let huge = [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01];
+
+	let mut opcodes = vec![0, 0, 1, 0];
+	opcodes.extend_from_slice(&huge);
+	assert_eq!(Program::from_opcodes(&opcodes), Err((0, RuntimeError::InvalidEncoding)));
+
+	let mut opcodes = vec![0, 1, 0]; // one '>', then a run that would overflow the length
+	opcodes.extend_from_slice(&huge);
+	assert_eq!(Program::from_opcodes(&opcodes), Err((2, RuntimeError::InvalidEncoding)));
+
+	let mut opcodes = vec![0];
+	opcodes.extend_from_slice(&huge);
+	assert_eq!(Program::from_opcodes(&opcodes), Err((0, RuntimeError::InvalidEncoding)));
+
+	let mut opcodes = vec![2, 0x80, 0x80, 0x80, 0x08]; // 1 << 24 '+'s, right at the limit
+	opcodes.extend_from_slice(&[2, 1]);
+	assert_eq!(Program::from_opcodes(&opcodes).err(), Some((5, RuntimeError::InvalidEncoding)));
+}
@@ -0,0 +1,148 @@
+extern crate forkengine;
+
+use forkengine::{RuntimeBuilder, RuntimeError, RuntimeProduct};
+
+fn run(instructions: &str, input: Vec<u8>, optimize: bool) -> RuntimeProduct {
+	RuntimeBuilder::new()
+		.instructions(instructions.to_string())
+		.input(input)
+		.optimize(optimize)
+		.build()
+		.run()
+}
+
+#[test]
+fn clear_loops_match_unoptimized_output() {
+	// echo each input byte, then the same cell after clearing it with one of the two idioms
+	let program = ",.[-]+.,.[+].,.[-].";
+	let input = vec![0, 200, 255];
+
+	let naive = run(program, input.clone(), false);
+	let optimized = run(program, input, true);
+
+	assert_eq!(naive.output, optimized.output);
+	assert_eq!(optimized.output, vec![0, 1, 200, 0, 255, 0]);
+	assert!(optimized.executions < naive.executions);
+	assert_eq!(optimized.error, None);
+}
+
+#[test]
+fn clear_loop_on_zero_cell_is_a_no_op() {
+	let naive = run("[-].+.", Vec::new(), false);
+	let optimized = run("[-].+.", Vec::new(), true);
+
+	assert_eq!(naive.output, vec![0, 1]);
+	assert_eq!(optimized.output, naive.output);
+}
+
+// prints the squares from 0 to 10000, one per line; its inner loops are mostly multiply and move loops
+const SQUARES: &str = "++++[>+++++<-]>[<+++++>-]+<+[>[>+>+<<-]++>>[<<+>>-]>>>[-]++>[-]+>>>+[[-]++++++>>>]<<<[[<++++++++<++>>-]+<.<[>----<-]<]<<[>>>>>[>>>[-]+++++++++<[>-<-]+++++++++>[-[<->-]+[<<<]]<[>+<-]>]<<-]<<-]";
+
+#[test]
+fn fused_multiply_loops_match_unoptimized_squares() {
+	let headless = |optimize: bool| RuntimeBuilder::new().instructions(SQUARES.to_string()).optimize(optimize).build().run_headless();
+	let (naive_output, naive_executions, _) = headless(false);
+	let (optimized_output, optimized_executions, _) = headless(true);
+
+	let expected: String = (0..101).map(|n| format!("{}\n", n * n)).collect();
+	assert_eq!(naive_output, expected.into_bytes());
+	assert_eq!(optimized_output, naive_output);
+	assert!(optimized_executions < naive_executions);
+}
+
+#[test]
+fn fused_multiply_loop_adds_every_multiple() {
+	// 7 * 3 into the next cell, 7 * 5 into the one after, and -7 * 2 into the one after that
+	let program = "+++++++[->+++>+++++>--<<<]>.>.>.<<<.";
+
+	let naive = run(program, Vec::new(), false);
+	let optimized = run(program, Vec::new(), true);
+
+	assert_eq!(naive.output, vec![21, 35, 242, 0]);
+	assert_eq!(optimized.output, naive.output);
+	assert!(optimized.executions < naive.executions);
+}
+
+#[test]
+fn loops_that_are_not_multiplies_still_run() {
+	// ends somewhere else, steps the counter by two, and does I/O respectively, so none of them can be fused
+	for program in &["+>++>+++<<[>]<.", "++++[>+<--]>.", "+++[>+.<-]"] {
+		let naive = run(program, Vec::new(), false);
+		let optimized = run(program, Vec::new(), true);
+
+		assert_eq!(optimized.output, naive.output, "program {:?}", program);
+		assert_eq!(optimized.error, naive.error, "program {:?}", program);
+	}
+}
+
+#[test]
+fn cells_a_fused_loop_only_passes_over_are_still_checked() {
+	for &optimize in &[false, true] {
+		let product = run("+[<>-].", Vec::new(), optimize);
+		assert_eq!(product.error, Some(RuntimeError::PointerUnderflow));
+
+		let product = RuntimeBuilder::new()
+			.instructions("+[>>><<<-].".to_string())
+			.memory_limit(2)
+			.optimize(optimize)
+			.build()
+			.run();
+		assert_eq!(product.error, Some(RuntimeError::MemoryLimitExceeded));
+
+		let product = run("+[>>+-<<-]", Vec::new(), optimize); // a cancelled addition still reaches its cell
+		assert_eq!(product.error, None);
+		assert_eq!(product.peak_memory, 2);
+	}
+}
+
+#[test]
+fn fused_loops_count_the_same_wraps() {
+	let cases: [(&str, bool, Vec<u8>, usize); 7] = [
+		("+[+]", false, Vec::new(), 1),
+		("+[-]", false, Vec::new(), 0),
+		("-[-]", true, Vec::new(), 1),
+		("-[->+<]", true, Vec::new(), 2),
+		("+++[->+<]", false, Vec::new(), 0),
+		("+[--+]", false, Vec::new(), 2),
+		("+[->+-+<]", false, vec![1, 255], 3)
+	];
+
+	for (instructions, signed, tape, wraps) in cases.iter().cloned() {
+		for &optimize in &[false, true] {
+			let product = RuntimeBuilder::new()
+				.instructions(instructions.to_string())
+				.initial_tape(tape.clone())
+				.signed_cells(signed)
+				.track_wraps(true)
+				.optimize(optimize)
+				.build()
+				.run();
+			assert_eq!(product.wraps, Some(wraps), "{} optimized: {}", instructions, optimize);
+		}
+	}
+}
+
+#[test]
+fn a_failing_fused_loop_leaves_the_same_tape() {
+	let cases: [(&str, Vec<u8>, usize); 3] = [
+		("+[-<+>]", Vec::new(), 0), // PointerUnderflow
+		("+[->+<]", Vec::new(), 1), // MemoryLimitExceeded
+		("+,,.[-<+>]", vec![7, 200], 0)
+	];
+
+	for (instructions, input, memory_limit) in cases.iter().cloned() {
+		let results: Vec<_> = [false, true].iter().map(|&optimize| {
+			let mut runtime = RuntimeBuilder::new()
+				.instructions(instructions.to_string())
+				.input(input.clone())
+				.memory_limit(memory_limit)
+				.optimize(optimize)
+				.build();
+			let product = runtime.run();
+			(product.error, product.output, product.snapshots.last().unwrap().memory.clone(), runtime.memory_slice().to_vec())
+		}).collect();
+
+		assert!(results[0].0.is_some(), "{}", instructions);
+		assert_eq!(results[0], results[1], "{}", instructions);
+	}
+}
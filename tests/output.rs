@@ -0,0 +1,58 @@
+extern crate forkengine;
+
+use forkengine::{RuntimeBuilder, Termination};
+
+#[test]
+fn output_is_pulled_one_byte_at_a_time() {
+	let mut runtime = RuntimeBuilder::new()
+		.instructions("++++++++[>++++++++<-]>+.+.+.".to_string())
+		.build();
+
+	assert_eq!(runtime.next_output_byte(), Some(b'A'));
+	assert_eq!(runtime.current_cell(), 65); // paused right after the '.'
+	assert_eq!(runtime.next_output_byte(), Some(b'B'));
+	assert_eq!(runtime.next_output_byte(), Some(b'C'));
+	assert_eq!(runtime.next_output_byte(), None);
+	assert_eq!(runtime.output_len(), 3);
+}
+
+#[test]
+fn a_silent_loop_gives_up_at_the_execution_limit() {
+	let mut runtime = RuntimeBuilder::new()
+		.instructions("+[]".to_string())
+		.execution_limit(100)
+		.build();
+
+	assert_eq!(runtime.next_output_byte(), None);
+}
+
+#[test]
+fn output_is_checked_against_the_expected_bytes() {
+	let run = |instructions: &str, expected: &[u8]| {
+		let mut runtime = RuntimeBuilder::new()
+			.instructions(instructions.to_string())
+			.execution_limit(1000)
+			.build();
+		let (matched, product) = runtime.run_against_expected(expected);
+		(matched, product.output, product.termination)
+	};
+
+	assert_eq!(run("+.+.", &[1, 2]), (true, vec![1, 2], Termination::Completed));
+	assert_eq!(run("+.+.+", &[1]), (false, vec![1, 2], Termination::Aborted));
+	assert_eq!(run("+.+.", &[1, 2, 3]), (false, vec![1, 2], Termination::Completed));
+	assert_eq!(run("+.+.+[.]", &[1, 3]), (false, vec![1, 2], Termination::Aborted)); // stops well before the limit
+}
+
+#[test]
+fn waiting_for_no_output_runs_nothing() {
+	let mut runtime = RuntimeBuilder::new().instructions("+.+.".to_string()).build();
+
+	let product = runtime.run_until_output(0);
+	assert_eq!(product.executions, 0);
+	assert_eq!(product.termination, Termination::Aborted);
+	assert_eq!(runtime.current_cell(), 0);
+
+	let product = runtime.run_until_output(1);
+	assert_eq!(product.output, vec![1]);
+	assert_eq!(product.executions, 2);
+}
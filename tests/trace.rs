@@ -0,0 +1,43 @@
+extern crate forkengine;
+
+use forkengine::{RuntimeBuilder, TapeMode};
+
+#[test]
+fn diff_reports_cells_pointer_and_output() {
+	let product = RuntimeBuilder::new()
+		.instructions("++.<-".to_string())
+		.tape_mode(TapeMode::Bidirectional)
+		.build()
+		.run();
+	let snapshots = &product.snapshots;
+
+	let diff = snapshots[0].diff(&snapshots[2]);
+	assert_eq!(diff.cells, vec![(0, 1, 2)]);
+	assert_eq!(diff.output, vec![2]);
+	assert_eq!(diff.pointer_movement, 0);
+
+	let diff = snapshots[2].diff(&snapshots[4]); // the tape grew to the left in between
+	assert_eq!(diff.cells, vec![(-1, 0, 255)]);
+	assert_eq!(diff.pointer_movement, -1);
+	assert!(diff.output.is_empty());
+
+	assert!(snapshots[4].diff(&snapshots[4]).cells.is_empty());
+}
+
+#[test]
+fn trimming_drops_only_the_high_zero_cells() {
+	let mut runtime = RuntimeBuilder::new()
+		.instructions("+>>+>>>-+<<<<".to_string())
+		.build();
+
+	let snapshot = runtime.run().snapshots.pop().unwrap();
+	assert_eq!(snapshot.memory, vec![1, 0, 1, 0, 0, 0]);
+
+	let trimmed = snapshot.trimmed();
+	assert_eq!(trimmed.memory, vec![1, 0, 1]);
+	assert_eq!(trimmed.memory_pointer, 1);
+	assert_eq!(snapshot.memory.len(), 6);
+
+	let mut runtime = RuntimeBuilder::new().instructions(">>>".to_string()).build();
+	assert_eq!(runtime.run().snapshots.pop().unwrap().trimmed().memory, vec![0, 0, 0, 0]); // down to the pointer
+}